@@ -1,5 +1,83 @@
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+
 use aoc_runner_derive::{aoc, aoc_generator};
 
+// Compares each sliding window of depth readings with the one before it. Two consecutive windows of size N
+// share N - 1 readings, so comparing their sums is the same as comparing the reading that left the window
+// with the one that entered it, which only requires holding on to the last N readings.
+pub struct WindowChanges<I> {
+    depths: I,
+    window: VecDeque<u32>,
+    window_size: usize,
+}
+
+impl<I> Iterator for WindowChanges<I>
+where
+    I: Iterator<Item = u32>,
+{
+    type Item = Ordering;
+
+    fn next(&mut self) -> Option<Ordering> {
+        while self.window.len() < self.window_size {
+            self.window.push_back(self.depths.next()?);
+        }
+
+        let entering = self.depths.next()?;
+        let leaving = self.window.pop_front().unwrap();
+        self.window.push_back(entering);
+        Some(entering.cmp(&leaving))
+    }
+}
+
+pub fn window_changes<I>(depths: I, window_size: usize) -> WindowChanges<I::IntoIter>
+where
+    I: IntoIterator<Item = u32>,
+{
+    assert!(window_size > 0, "Window size must be at least 1");
+    WindowChanges {
+        depths: depths.into_iter(),
+        window: VecDeque::with_capacity(window_size),
+        window_size,
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct DepthReport {
+    pub increases: usize,
+    pub decreases: usize,
+    // Most increases in a row
+    pub longest_increasing_run: usize,
+    // Index of every window that was deeper than the one before it, where window `i` starts at reading `i`
+    pub increase_positions: Vec<usize>,
+}
+
+impl DepthReport {
+    pub fn new<I>(depths: I, window_size: usize) -> Self
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        let mut report = Self::default();
+        let mut current_run = 0;
+        for (index, change) in window_changes(depths, window_size).enumerate() {
+            match change {
+                Ordering::Greater => {
+                    report.increases += 1;
+                    report.increase_positions.push(index + 1);
+                    current_run += 1;
+                    report.longest_increasing_run = report.longest_increasing_run.max(current_run);
+                }
+                Ordering::Less => {
+                    report.decreases += 1;
+                    current_run = 0;
+                }
+                Ordering::Equal => current_run = 0,
+            }
+        }
+        report
+    }
+}
+
 #[aoc_generator(day1)]
 pub fn generator(input: &str) -> Vec<u32> {
     input
@@ -9,38 +87,75 @@ pub fn generator(input: &str) -> Vec<u32> {
 }
 
 #[aoc(day1, part1)]
-pub fn part1(depths: &[u32]) -> u32 {
-    let count_increases = |(count, previous), value| {
-        if value > previous {
-            (count + 1, value)
-        } else {
-            (count, value)
-        }
-    };
-
-    let initial_value = (0, &depths[0]);
-    let (increases, _) = depths.iter().skip(1).fold(initial_value, count_increases);
-    increases
+pub fn part1(depths: &[u32]) -> usize {
+    DepthReport::new(depths.iter().copied(), 1).increases
 }
 
 #[aoc(day1, part2)]
-pub fn part2(depths: &[u32]) -> u32 {
-    let count_increases = |(count, previous), value| {
-        if value > previous {
-            (count + 1, value)
-        } else {
-            (count, value)
-        }
-    };
-
-    let sliding_windows = (0..depths.len() - 2)
-        .map(|index| depths[index] + depths[index + 1] + depths[index + 2])
-        .collect::<Vec<_>>();
-
-    let initial_value = (0, &sliding_windows[0]);
-    let (increases, _) = sliding_windows
-        .iter()
-        .skip(1)
-        .fold(initial_value, count_increases);
-    increases
+pub fn part2(depths: &[u32]) -> usize {
+    DepthReport::new(depths.iter().copied(), 3).increases
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "199\n\
+                         200\n\
+                         208\n\
+                         210\n\
+                         200\n\
+                         207\n\
+                         240\n\
+                         269\n\
+                         260\n\
+                         263";
+
+    #[test]
+    fn test_part1() {
+        assert_eq!(part1(&generator(INPUT)), 7);
+    }
+
+    #[test]
+    fn test_part2() {
+        assert_eq!(part2(&generator(INPUT)), 5);
+    }
+
+    #[test]
+    fn test_report() {
+        let report = DepthReport::new(generator(INPUT), 3);
+        assert_eq!(
+            report,
+            DepthReport {
+                increases: 5,
+                decreases: 1,
+                longest_increasing_run: 4,
+                increase_positions: vec![1, 4, 5, 6, 7],
+            }
+        );
+    }
+
+    #[test]
+    fn test_short_input() {
+        assert_eq!(part1(&[]), 0);
+        assert_eq!(part2(&[100, 200, 300]), 0);
+        assert_eq!(DepthReport::new([5, 1, 2], 5), DepthReport::default());
+    }
+
+    #[test]
+    fn test_streaming() {
+        // An endless stream that goes down and then up again, only consumed as far as needed
+        let depths = (0..).map(|i: u32| (i % 10).abs_diff(5));
+        let changes = window_changes(depths, 4).take(5).collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            [
+                Ordering::Less,
+                Ordering::Less,
+                Ordering::Less,
+                Ordering::Equal,
+                Ordering::Greater
+            ]
+        );
+    }
 }
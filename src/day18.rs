@@ -0,0 +1,344 @@
+use std::fmt::{Debug, Display};
+use std::iter::Sum;
+use std::ops::Add;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Result};
+use aoc_runner_derive::{aoc, aoc_generator};
+use itertools::Itertools;
+
+#[derive(Clone, PartialEq)]
+pub enum SnailfishNumber {
+    Regular(u32),
+    Pair(Box<SnailfishNumber>, Box<SnailfishNumber>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReductionAction {
+    Explode,
+    Split,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReductionStep {
+    pub action: ReductionAction,
+    // The whole number as it looks right after this action was applied
+    pub result: SnailfishNumber,
+}
+
+impl SnailfishNumber {
+    fn pair(left: Self, right: Self) -> Self {
+        Self::Pair(Box::new(left), Box::new(right))
+    }
+
+    pub fn magnitude(&self) -> u32 {
+        match self {
+            Self::Regular(value) => *value,
+            Self::Pair(left, right) => 3 * left.magnitude() + 2 * right.magnitude(),
+        }
+    }
+
+    // Apply explodes and splits until neither applies anymore, reporting every action taken to `observe`
+    fn reduce_with<F>(&mut self, mut observe: F)
+    where
+        F: FnMut(ReductionAction, &Self),
+    {
+        loop {
+            if self.explode(0).is_some() {
+                observe(ReductionAction::Explode, self);
+            } else if self.split() {
+                observe(ReductionAction::Split, self);
+            } else {
+                break;
+            }
+        }
+    }
+
+    pub fn reduce(&mut self) {
+        self.reduce_with(|_, _| {});
+    }
+
+    // Same as `reduce`, but records every intermediate number so the explode/split ordering can be inspected
+    pub fn reduce_traced(&mut self) -> Vec<ReductionStep> {
+        let mut steps = Vec::new();
+        self.reduce_with(|action, result| {
+            steps.push(ReductionStep {
+                action,
+                result: result.clone(),
+            })
+        });
+        steps
+    }
+
+    // Same as `+`, but also returns the reduction steps taken after joining the two numbers
+    pub fn add_traced(self, rhs: Self) -> (Self, Vec<ReductionStep>) {
+        let mut sum = Self::pair(self, rhs);
+        let steps = sum.reduce_traced();
+        (sum, steps)
+    }
+
+    fn add_to_leftmost(&mut self, value: u32) {
+        match self {
+            Self::Regular(v) => *v += value,
+            Self::Pair(left, _) => left.add_to_leftmost(value),
+        }
+    }
+
+    fn add_to_rightmost(&mut self, value: u32) {
+        match self {
+            Self::Regular(v) => *v += value,
+            Self::Pair(_, right) => right.add_to_rightmost(value),
+        }
+    }
+
+    // Explode the leftmost pair nested inside four pairs, if any. Returns the values that still need to be
+    // carried to the left and right of the exploded pair (None if nothing exploded).
+    fn explode(&mut self, depth: usize) -> Option<(Option<u32>, Option<u32>)> {
+        let (left, right) = match self {
+            Self::Regular(_) => return None,
+            Self::Pair(left, right) => (left, right),
+        };
+
+        if depth >= 4 {
+            if let (Self::Regular(l), Self::Regular(r)) = (left.as_ref(), right.as_ref()) {
+                let carry = (Some(*l), Some(*r));
+                *self = Self::Regular(0);
+                return Some(carry);
+            }
+        }
+
+        if let Some((carry_left, carry_right)) = left.explode(depth + 1) {
+            if let Some(value) = carry_right {
+                right.add_to_leftmost(value);
+            }
+            return Some((carry_left, None));
+        }
+
+        if let Some((carry_left, carry_right)) = right.explode(depth + 1) {
+            if let Some(value) = carry_left {
+                left.add_to_rightmost(value);
+            }
+            return Some((None, carry_right));
+        }
+
+        None
+    }
+
+    // Split the leftmost regular number that is 10 or greater, returning whether anything was split
+    fn split(&mut self) -> bool {
+        match self {
+            Self::Regular(value) if *value >= 10 => {
+                let half = *value / 2;
+                *self = Self::pair(Self::Regular(half), Self::Regular(*value - half));
+                true
+            }
+            Self::Regular(_) => false,
+            Self::Pair(left, right) => left.split() || right.split(),
+        }
+    }
+
+    fn parse_from(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<Self> {
+        match chars.next() {
+            Some('[') => {
+                let left = Self::parse_from(chars)?;
+                if chars.next() != Some(',') {
+                    bail!("Expected ',' between pair elements");
+                }
+                let right = Self::parse_from(chars)?;
+                if chars.next() != Some(']') {
+                    bail!("Expected ']' after pair");
+                }
+                Ok(Self::pair(left, right))
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let mut value = c.to_digit(10).unwrap();
+                while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+                    value = value * 10 + digit;
+                    chars.next();
+                }
+                Ok(Self::Regular(value))
+            }
+            Some(c) => Err(anyhow!("Unexpected character '{}'", c)),
+            None => Err(anyhow!("Unexpected end of input")),
+        }
+    }
+}
+
+impl FromStr for SnailfishNumber {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut chars = s.trim().chars().peekable();
+        let number = Self::parse_from(&mut chars)?;
+        if let Some(c) = chars.next() {
+            bail!("Trailing character '{}' after snailfish number", c);
+        }
+        Ok(number)
+    }
+}
+
+impl Display for SnailfishNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Regular(value) => write!(f, "{}", value),
+            Self::Pair(left, right) => write!(f, "[{},{}]", left, right),
+        }
+    }
+}
+
+impl Debug for SnailfishNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl Add for SnailfishNumber {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        let mut sum = Self::pair(self, rhs);
+        sum.reduce();
+        sum
+    }
+}
+
+// There's no snailfish number that adding leaves unchanged, so this panics on an empty list. Anything that
+// might be empty has to check first.
+impl Sum for SnailfishNumber {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.reduce(|sum, number| sum + number)
+            .expect("Can't sum an empty list of snailfish numbers")
+    }
+}
+
+impl<'a> Sum<&'a SnailfishNumber> for SnailfishNumber {
+    fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.cloned().sum()
+    }
+}
+
+#[aoc_generator(day18)]
+pub fn generator(input: &str) -> Result<Vec<SnailfishNumber>> {
+    input
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+#[aoc(day18, part1)]
+pub fn part1(numbers: &[SnailfishNumber]) -> Option<u32> {
+    let sum = numbers.iter().cloned().reduce(|sum, number| sum + number)?;
+    Some(sum.magnitude())
+}
+
+#[aoc(day18, part2)]
+pub fn part2(numbers: &[SnailfishNumber]) -> Option<u32> {
+    numbers
+        .iter()
+        .permutations(2)
+        .map(|pair| (pair[0].clone() + pair[1].clone()).magnitude())
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "[[[0,[5,8]],[[1,7],[9,6]]],[[4,[1,2]],[[1,4],2]]]\n\
+                         [[[5,[2,8]],4],[5,[[9,9],0]]]\n\
+                         [6,[[[6,2],[5,6]],[[7,6],[4,7]]]]\n\
+                         [[[6,[0,7]],[0,9]],[4,[9,[9,0]]]]\n\
+                         [[[7,[6,4]],[3,[1,3]]],[[[5,5],1],9]]\n\
+                         [[6,[[7,3],[3,2]]],[[[3,8],[5,7]],4]]\n\
+                         [[[[5,4],[7,7]],8],[[8,3],8]]\n\
+                         [[9,3],[[9,9],[6,[4,9]]]]\n\
+                         [[2,[[7,7],7]],[[5,8],[[9,3],[0,2]]]]\n\
+                         [[[[5,2],5],[8,[3,7]]],[[5,[7,5]],[4,4]]]";
+
+    fn parse(s: &str) -> SnailfishNumber {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        for line in INPUT.lines() {
+            assert_eq!(parse(line).to_string(), line);
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!("[1,2".parse::<SnailfishNumber>().is_err());
+        assert!("[1;2]".parse::<SnailfishNumber>().is_err());
+        assert!("[1,2]]".parse::<SnailfishNumber>().is_err());
+    }
+
+    #[test]
+    fn test_add_trace() {
+        let (sum, steps) = parse("[[[[4,3],4],4],[7,[[8,4],9]]]").add_traced(parse("[1,1]"));
+        let steps = steps
+            .into_iter()
+            .map(|step| (step.action, step.result.to_string()))
+            .collect_vec();
+        assert_eq!(
+            steps,
+            vec![
+                (
+                    ReductionAction::Explode,
+                    "[[[[0,7],4],[7,[[8,4],9]]],[1,1]]".to_owned()
+                ),
+                (
+                    ReductionAction::Explode,
+                    "[[[[0,7],4],[15,[0,13]]],[1,1]]".to_owned()
+                ),
+                (
+                    ReductionAction::Split,
+                    "[[[[0,7],4],[[7,8],[0,13]]],[1,1]]".to_owned()
+                ),
+                (
+                    ReductionAction::Split,
+                    "[[[[0,7],4],[[7,8],[0,[6,7]]]],[1,1]]".to_owned()
+                ),
+                (
+                    ReductionAction::Explode,
+                    "[[[[0,7],4],[[7,8],[6,0]]],[8,1]]".to_owned()
+                ),
+            ]
+        );
+        assert_eq!(sum.to_string(), "[[[[0,7],4],[[7,8],[6,0]]],[8,1]]");
+    }
+
+    #[test]
+    fn test_traced_sum() {
+        // Tracing doesn't change the sum, and every trace ends with the sum it produced
+        let numbers = generator(INPUT).unwrap();
+        let mut sum = numbers[0].clone();
+        for number in &numbers[1..] {
+            let (traced, steps) = sum.clone().add_traced(number.clone());
+            assert_eq!(steps.last().map(|step| &step.result), Some(&traced));
+            sum = sum + number.clone();
+            assert_eq!(traced, sum);
+        }
+        assert_eq!(sum.magnitude(), 4140);
+    }
+
+    #[test]
+    fn test_magnitude() {
+        assert_eq!(
+            parse("[[[[8,7],[7,7]],[[8,6],[7,7]]],[[[0,7],[6,6]],[8,7]]]").magnitude(),
+            3488
+        );
+    }
+
+    #[test]
+    fn test_part1() {
+        assert_eq!(part1(&generator(INPUT).unwrap()), Some(4140));
+        assert_eq!(part1(&generator("").unwrap()), None);
+    }
+
+    #[test]
+    fn test_part2() {
+        assert_eq!(part2(&generator(INPUT).unwrap()), Some(3993));
+    }
+}
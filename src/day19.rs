@@ -0,0 +1,403 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use aoc_2021::Point3;
+use aoc_runner_derive::{aoc, aoc_generator};
+use itertools::Itertools;
+
+// Number of beacons two scanners need to have in common for us to consider them aligned
+const MIN_OVERLAP: usize = 12;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rotation {
+    // Row i of the matrix has a single non-zero entry, `signs[i]`, in column `axes[i]`
+    axes: [usize; 3],
+    signs: [i32; 3],
+}
+
+impl Rotation {
+    // All 24 proper rotations that map axes onto axes, i.e. the signed permutation matrices with determinant 1
+    pub fn all() -> Vec<Self> {
+        (0..3)
+            .permutations(3)
+            .cartesian_product(itertools::repeat_n([1, -1], 3).multi_cartesian_product())
+            .map(|(axes, signs)| Self {
+                axes: [axes[0], axes[1], axes[2]],
+                signs: [signs[0], signs[1], signs[2]],
+            })
+            .filter(|rotation| rotation.determinant() == 1)
+            .collect()
+    }
+
+    fn determinant(&self) -> i32 {
+        let inversions = (0..3)
+            .tuple_combinations()
+            .filter(|&(i, j)| self.axes[i] > self.axes[j])
+            .count();
+        let parity = if inversions % 2 == 0 { 1 } else { -1 };
+        parity * self.signs.iter().product::<i32>()
+    }
+
+    pub fn apply(&self, p: Point3) -> Point3 {
+        let coordinates = [p.x, p.y, p.z];
+        Point3::new(
+            self.signs[0] * coordinates[self.axes[0]],
+            self.signs[1] * coordinates[self.axes[1]],
+            self.signs[2] * coordinates[self.axes[2]],
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Scanner {
+    pub beacons: Vec<Point3>,
+    // Number of beacon pairs at each squared distance, which doesn't change with rotation or translation
+    fingerprint: HashMap<i32, usize>,
+}
+
+impl Scanner {
+    fn new(beacons: Vec<Point3>) -> Self {
+        let mut fingerprint = HashMap::new();
+        for (a, b) in beacons.iter().tuple_combinations() {
+            let d = *a - *b;
+            *fingerprint
+                .entry(d.x * d.x + d.y * d.y + d.z * d.z)
+                .or_insert(0) += 1;
+        }
+
+        Self {
+            beacons,
+            fingerprint,
+        }
+    }
+
+    // Cheap check whether two scanners could possibly share MIN_OVERLAP beacons: if they do, they also share
+    // all the pairwise distances between those beacons
+    fn might_overlap(&self, other: &Self) -> bool {
+        let shared_distances = self
+            .fingerprint
+            .iter()
+            .filter_map(|(distance, count)| {
+                other
+                    .fingerprint
+                    .get(distance)
+                    .map(|other_count| *count.min(other_count))
+            })
+            .sum::<usize>();
+        shared_distances >= MIN_OVERLAP * (MIN_OVERLAP - 1) / 2
+    }
+
+    // Find the rotation and position of this scanner relative to the frame that `reference` is expressed in
+    pub fn align(&self, reference: &[Point3]) -> Option<(Rotation, Point3)> {
+        Rotation::all().into_iter().find_map(|rotation| {
+            let mut offset_votes = HashMap::new();
+            for beacon in &self.beacons {
+                let beacon = rotation.apply(*beacon);
+                for reference_beacon in reference {
+                    let votes = offset_votes.entry(*reference_beacon - beacon).or_insert(0);
+                    *votes += 1;
+                    if *votes >= MIN_OVERLAP {
+                        return Some((rotation, *reference_beacon - beacon));
+                    }
+                }
+            }
+            None
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct BeaconMap {
+    pub beacons: HashSet<Point3>,
+    // Position of each scanner, indexed by scanner id, relative to scanner 0
+    pub scanners: Vec<Point3>,
+}
+
+impl BeaconMap {
+    // Align every scanner to scanner 0, returning None if any of them can't be connected to the rest
+    pub fn assemble(scanners: &[Scanner]) -> Option<Self> {
+        let mut aligned = vec![None; scanners.len()];
+        aligned[0] = Some((Point3::new(0, 0, 0), scanners.first()?.beacons.clone()));
+
+        let mut queue = VecDeque::from([0]);
+        while let Some(reference_index) = queue.pop_front() {
+            let reference_beacons = aligned[reference_index].as_ref().unwrap().1.clone();
+            for (index, scanner) in scanners.iter().enumerate() {
+                if aligned[index].is_some() || !scanner.might_overlap(&scanners[reference_index]) {
+                    continue;
+                }
+
+                if let Some((rotation, position)) = scanner.align(&reference_beacons) {
+                    let beacons = scanner
+                        .beacons
+                        .iter()
+                        .map(|beacon| rotation.apply(*beacon) + position)
+                        .collect();
+                    aligned[index] = Some((position, beacons));
+                    queue.push_back(index);
+                }
+            }
+        }
+
+        let aligned = aligned.into_iter().collect::<Option<Vec<_>>>()?;
+        Some(Self {
+            beacons: aligned
+                .iter()
+                .flat_map(|(_, beacons)| beacons.iter().copied())
+                .collect(),
+            scanners: aligned.into_iter().map(|(position, _)| position).collect(),
+        })
+    }
+
+    pub fn max_scanner_distance(&self) -> i32 {
+        self.scanners
+            .iter()
+            .tuple_combinations()
+            .map(|(a, b)| a.manhattan_distance(b))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+#[aoc_generator(day19)]
+pub fn generator(input: &str) -> Vec<Scanner> {
+    input
+        .split("\n\n")
+        .map(|report| {
+            let beacons = report
+                .lines()
+                .filter(|line| !line.starts_with("---"))
+                .filter_map(|line| {
+                    line.split(',')
+                        .map(str::parse::<i32>)
+                        .filter_map(|v| v.ok())
+                        .collect_tuple()
+                })
+                .map(|(x, y, z)| Point3::new(x, y, z))
+                .collect();
+            Scanner::new(beacons)
+        })
+        .collect()
+}
+
+#[aoc(day19, part1)]
+pub fn part1(scanners: &[Scanner]) -> Option<usize> {
+    BeaconMap::assemble(scanners).map(|map| map.beacons.len())
+}
+
+#[aoc(day19, part2)]
+pub fn part2(scanners: &[Scanner]) -> Option<i32> {
+    BeaconMap::assemble(scanners).map(|map| map.max_scanner_distance())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "--- scanner 0 ---\n\
+                         404,-588,-901\n\
+                         528,-643,409\n\
+                         -838,591,734\n\
+                         390,-675,-793\n\
+                         -537,-823,-458\n\
+                         -485,-357,347\n\
+                         -345,-311,381\n\
+                         -661,-816,-575\n\
+                         -876,649,763\n\
+                         -618,-824,-621\n\
+                         553,345,-567\n\
+                         474,580,667\n\
+                         -447,-329,318\n\
+                         -584,868,-557\n\
+                         544,-627,-890\n\
+                         564,392,-477\n\
+                         455,729,728\n\
+                         -892,524,684\n\
+                         -689,845,-530\n\
+                         423,-701,434\n\
+                         7,-33,-71\n\
+                         630,319,-379\n\
+                         443,580,662\n\
+                         -789,900,-551\n\
+                         459,-707,401\n\n\
+                         --- scanner 1 ---\n\
+                         686,422,578\n\
+                         605,423,415\n\
+                         515,917,-361\n\
+                         -336,658,858\n\
+                         95,138,22\n\
+                         -476,619,847\n\
+                         -340,-569,-846\n\
+                         567,-361,727\n\
+                         -460,603,-452\n\
+                         669,-402,600\n\
+                         729,430,532\n\
+                         -500,-761,534\n\
+                         -322,571,750\n\
+                         -466,-666,-811\n\
+                         -429,-592,574\n\
+                         -355,545,-477\n\
+                         703,-491,-529\n\
+                         -328,-685,520\n\
+                         413,935,-424\n\
+                         -391,539,-444\n\
+                         586,-435,557\n\
+                         -364,-763,-893\n\
+                         807,-499,-711\n\
+                         755,-354,-619\n\
+                         553,889,-390\n\n\
+                         --- scanner 2 ---\n\
+                         649,640,665\n\
+                         682,-795,504\n\
+                         -784,533,-524\n\
+                         -644,584,-595\n\
+                         -588,-843,648\n\
+                         -30,6,44\n\
+                         -674,560,763\n\
+                         500,723,-460\n\
+                         609,671,-379\n\
+                         -555,-800,653\n\
+                         -675,-892,-343\n\
+                         697,-426,-610\n\
+                         578,704,681\n\
+                         493,664,-388\n\
+                         -671,-858,530\n\
+                         -667,343,800\n\
+                         571,-461,-707\n\
+                         -138,-166,112\n\
+                         -889,563,-600\n\
+                         646,-828,498\n\
+                         640,759,510\n\
+                         -630,509,768\n\
+                         -681,-892,-333\n\
+                         673,-379,-804\n\
+                         -742,-814,-386\n\
+                         577,-820,562\n\n\
+                         --- scanner 3 ---\n\
+                         -589,542,597\n\
+                         605,-692,669\n\
+                         -500,565,-823\n\
+                         -660,373,557\n\
+                         -458,-679,-417\n\
+                         -488,449,543\n\
+                         -626,468,-788\n\
+                         338,-750,-386\n\
+                         528,-832,-391\n\
+                         562,-778,733\n\
+                         -938,-730,414\n\
+                         543,643,-506\n\
+                         -524,371,-870\n\
+                         407,773,750\n\
+                         -104,29,83\n\
+                         378,-903,-323\n\
+                         -778,-728,485\n\
+                         426,699,580\n\
+                         -438,-605,-362\n\
+                         -469,-447,-387\n\
+                         509,732,623\n\
+                         647,635,-688\n\
+                         -868,-804,481\n\
+                         614,-800,639\n\
+                         595,780,-596\n\n\
+                         --- scanner 4 ---\n\
+                         727,592,562\n\
+                         -293,-554,779\n\
+                         441,611,-461\n\
+                         -714,465,-776\n\
+                         -743,427,-804\n\
+                         -660,-479,-426\n\
+                         832,-632,460\n\
+                         927,-485,-438\n\
+                         408,393,-506\n\
+                         466,436,-512\n\
+                         110,16,151\n\
+                         -258,-428,682\n\
+                         -393,719,612\n\
+                         -211,-452,876\n\
+                         808,-476,-593\n\
+                         -575,615,604\n\
+                         -485,667,467\n\
+                         -680,325,-822\n\
+                         -627,-443,-432\n\
+                         872,-547,-609\n\
+                         833,512,582\n\
+                         807,604,487\n\
+                         839,-516,451\n\
+                         891,-625,532\n\
+                         -652,-548,-490\n\
+                         30,-46,-14";
+
+    // Beacons as seen from scanner 0, scattered around so that all pairwise distances are distinct
+    fn beacons() -> Vec<Point3> {
+        (1..=14)
+            .map(|i| Point3::new(i * 37 % 500, i * i * 11 % 700 - 350, i * i * i % 613))
+            .collect()
+    }
+
+    fn render(scanners: &[Vec<Point3>]) -> String {
+        scanners
+            .iter()
+            .enumerate()
+            .map(|(id, beacons)| {
+                let lines = beacons
+                    .iter()
+                    .map(|b| format!("{},{},{}", b.x, b.y, b.z))
+                    .join("\n");
+                format!("--- scanner {} ---\n{}", id, lines)
+            })
+            .join("\n\n")
+    }
+
+    #[test]
+    fn test_rotations() {
+        let rotations = Rotation::all();
+        assert_eq!(rotations.len(), 24);
+
+        let p = Point3::new(1, 2, 3);
+        let images = rotations.iter().map(|r| r.apply(p)).unique().count();
+        assert_eq!(images, 24);
+    }
+
+    #[test]
+    fn test_assemble() {
+        let rotations = Rotation::all();
+        let rotation = rotations[17];
+        let inverse = rotations
+            .iter()
+            .find(|r| r.apply(rotation.apply(Point3::new(1, 2, 3))) == Point3::new(1, 2, 3))
+            .unwrap();
+        let position = Point3::new(68, -1246, -43);
+
+        // Scanner 1 sees the same beacons plus one extra, in its own orientation and relative to its own position
+        let mut seen_by_1 = beacons();
+        seen_by_1.push(Point3::new(900, 900, 900));
+        let seen_by_1 = seen_by_1
+            .into_iter()
+            .map(|b| inverse.apply(b - position))
+            .collect_vec();
+
+        let map = BeaconMap::assemble(&generator(&render(&[beacons(), seen_by_1]))).unwrap();
+        assert_eq!(map.scanners, vec![Point3::new(0, 0, 0), position]);
+        assert_eq!(map.beacons.len(), 15);
+        assert_eq!(map.max_scanner_distance(), 68 + 1246 + 43);
+    }
+
+    #[test]
+    fn test_part1() {
+        assert_eq!(part1(&generator(INPUT)), Some(79));
+    }
+
+    #[test]
+    fn test_part2() {
+        assert_eq!(part2(&generator(INPUT)), Some(3621));
+        assert_eq!(
+            BeaconMap::assemble(&generator(INPUT)).unwrap().scanners,
+            vec![
+                Point3::new(0, 0, 0),
+                Point3::new(68, -1246, -43),
+                Point3::new(1105, -1205, 1229),
+                Point3::new(-92, -2380, -20),
+                Point3::new(-20, -1133, 1061),
+            ]
+        );
+    }
+}
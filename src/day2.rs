@@ -1,58 +1,265 @@
+use anyhow::{anyhow, bail, Result};
 use aoc_runner_derive::{aoc, aoc_generator};
 
-use itertools::Itertools;
-
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Command {
-    Forward(u32),
-    Down(u32),
-    Up(u32),
+    Forward(i64),
+    Back(i64),
+    Down(i64),
+    Up(i64),
+    Wait,
+    Repeat(usize, Vec<Command>),
 }
 
 impl Command {
-    fn try_parse(s: &str) -> Option<Self> {
-        if let Some((command, offset)) = s.split_whitespace().collect_tuple() {
-            let offset = offset.parse().unwrap();
-            match command {
-                "forward" => Some(Command::Forward(offset)),
-                "down" => Some(Command::Down(offset)),
-                "up" => Some(Command::Up(offset)),
-                _ => None,
+    // Parse commands until the end of the input or until a matching `end` line when inside a repeat block
+    fn parse_block<'a, I>(lines: &mut I, in_repeat: bool) -> Result<Vec<Self>>
+    where
+        I: Iterator<Item = (usize, &'a str)>,
+    {
+        let mut commands = Vec::new();
+        while let Some((index, line)) = lines.next() {
+            let line_number = index + 1;
+            let mut parts = line.split_whitespace();
+            let command = match parts.next() {
+                None => continue,
+                Some("end") if in_repeat => return Ok(commands),
+                Some("end") => bail!("Line {}: 'end' without a matching 'repeat'", line_number),
+                Some(command) => command,
+            };
+
+            let argument = parts
+                .next()
+                .map(|argument| {
+                    argument.parse::<i64>().map_err(|_| {
+                        anyhow!("Line {}: invalid argument '{}'", line_number, argument)
+                    })
+                })
+                .transpose()?;
+            if parts.next().is_some() {
+                bail!("Line {}: too many arguments in '{}'", line_number, line);
+            }
+
+            commands.push(match (command, argument) {
+                ("forward", Some(distance)) => Self::Forward(distance),
+                ("back", Some(distance)) => Self::Back(distance),
+                ("down", Some(distance)) => Self::Down(distance),
+                ("up", Some(distance)) => Self::Up(distance),
+                ("wait", None) => Self::Wait,
+                ("repeat", Some(count)) if count >= 0 => {
+                    Self::Repeat(count as usize, Self::parse_block(lines, true)?)
+                }
+                _ => bail!("Line {}: invalid command '{}'", line_number, line),
+            });
+        }
+
+        if in_repeat {
+            bail!("Missing 'end' for 'repeat' block");
+        }
+        Ok(commands)
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Position {
+    pub horizontal: i64,
+    // Negative depths are above the surface
+    pub depth: i64,
+}
+
+pub trait Submarine {
+    // Apply a single command. `Repeat` blocks are unrolled by `dive` and never passed in here.
+    fn execute(&mut self, command: &Command);
+    fn position(&self) -> Position;
+}
+
+// Up and down move the submarine directly
+#[derive(Debug, Default)]
+pub struct SimpleSubmarine {
+    position: Position,
+}
+
+impl Submarine for SimpleSubmarine {
+    fn execute(&mut self, command: &Command) {
+        match command {
+            Command::Forward(distance) => self.position.horizontal += distance,
+            Command::Back(distance) => self.position.horizontal -= distance,
+            Command::Down(distance) => self.position.depth += distance,
+            Command::Up(distance) => self.position.depth -= distance,
+            Command::Wait | Command::Repeat(_, _) => {}
+        }
+    }
+
+    fn position(&self) -> Position {
+        self.position
+    }
+}
+
+// Up and down adjust the aim, and moving forward or back changes depth along that aim
+#[derive(Debug, Default)]
+pub struct AimingSubmarine {
+    position: Position,
+    aim: i64,
+}
+
+impl Submarine for AimingSubmarine {
+    fn execute(&mut self, command: &Command) {
+        match command {
+            Command::Forward(distance) => {
+                self.position.horizontal += distance;
+                self.position.depth += self.aim * distance;
+            }
+            Command::Back(distance) => {
+                self.position.horizontal -= distance;
+                self.position.depth -= self.aim * distance;
+            }
+            Command::Down(distance) => self.aim += distance,
+            Command::Up(distance) => self.aim -= distance,
+            Command::Wait | Command::Repeat(_, _) => {}
+        }
+    }
+
+    fn position(&self) -> Position {
+        self.position
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Dive {
+    // Every command that was executed, with `Repeat` blocks unrolled, and the position right after it
+    pub trajectory: Vec<(Command, Position)>,
+    // Indices into `trajectory` where the submarine came up from underwater to the surface or above it.
+    // Depth 0 is the surface, so going up from there doesn't count again.
+    pub surfacings: Vec<usize>,
+}
+
+impl Dive {
+    pub fn final_position(&self) -> Position {
+        self.trajectory
+            .last()
+            .map(|(_, position)| *position)
+            .unwrap_or_default()
+    }
+
+    fn record<S: Submarine>(&mut self, submarine: &mut S, commands: &[Command]) {
+        for command in commands {
+            if let Command::Repeat(count, block) = command {
+                for _ in 0..*count {
+                    self.record(submarine, block);
+                }
+                continue;
             }
-        } else {
-            None
+
+            let was_underwater = self.final_position().depth > 0;
+            submarine.execute(command);
+            let position = submarine.position();
+            if was_underwater && position.depth <= 0 {
+                self.surfacings.push(self.trajectory.len());
+            }
+            self.trajectory.push((command.clone(), position));
         }
     }
 }
 
+pub fn dive<S: Submarine>(mut submarine: S, commands: &[Command]) -> Dive {
+    let mut dive = Dive::default();
+    dive.record(&mut submarine, commands);
+    dive
+}
+
 #[aoc_generator(day2)]
-pub fn generator(input: &str) -> Vec<Command> {
-    input
-        .lines()
-        .filter_map(|line| Command::try_parse(line))
-        .collect()
+pub fn generator(input: &str) -> Result<Vec<Command>> {
+    Command::parse_block(&mut input.lines().enumerate(), false)
 }
 
 #[aoc(day2, part1)]
-pub fn part1(commands: &[Command]) -> u32 {
-    let (x, y) = commands
-        .iter()
-        .fold((0, 0), |(x, y), command| match command {
-            Command::Forward(offset) => (x + offset, y),
-            Command::Down(offset) => (x, y + offset),
-            Command::Up(offset) => (x, y - offset),
-        });
-    x * y
+pub fn part1(commands: &[Command]) -> i64 {
+    let position = dive(SimpleSubmarine::default(), commands).final_position();
+    position.horizontal * position.depth
 }
 
 #[aoc(day2, part2)]
-pub fn part2(commands: &[Command]) -> u32 {
-    let (_, x, y) = commands
-        .iter()
-        .fold((0, 0, 0), |(aim, x, y), command| match command {
-            Command::Forward(offset) => (aim, x + offset, y + aim * offset),
-            Command::Down(offset) => (aim + offset, x, y),
-            Command::Up(offset) => (aim - offset, x, y),
-        });
-    x * y
+pub fn part2(commands: &[Command]) -> i64 {
+    let position = dive(AimingSubmarine::default(), commands).final_position();
+    position.horizontal * position.depth
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "forward 5\n\
+                         down 5\n\
+                         forward 8\n\
+                         up 3\n\
+                         down 8\n\
+                         forward 2";
+
+    #[test]
+    fn test_part1() {
+        assert_eq!(part1(&generator(INPUT).unwrap()), 150);
+    }
+
+    #[test]
+    fn test_part2() {
+        assert_eq!(part2(&generator(INPUT).unwrap()), 900);
+    }
+
+    #[test]
+    fn test_script() {
+        let commands = generator(
+            "down 1\n\
+             repeat 3\n\
+             forward 2\n\
+             repeat 2\n\
+             wait\n\
+             end\n\
+             end\n\
+             back 1",
+        )
+        .unwrap();
+        let dive = dive(AimingSubmarine::default(), &commands);
+        assert_eq!(dive.trajectory.len(), 1 + 3 * 3 + 1);
+        assert_eq!(
+            dive.final_position(),
+            Position {
+                horizontal: 5,
+                depth: 5
+            }
+        );
+    }
+
+    #[test]
+    fn test_surfacing() {
+        let commands = generator("down 2\nup 3\ndown 5\nup 10\nup 1").unwrap();
+        let dive = dive(SimpleSubmarine::default(), &commands);
+        assert_eq!(dive.final_position().depth, -7);
+        assert_eq!(dive.surfacings, vec![1, 3]);
+    }
+
+    #[test]
+    fn test_surfacing_from_surface() {
+        // Reaching depth 0 counts, but going up from there doesn't, whether the dive starts there or comes
+        // back up to it
+        let commands = generator("up 1\ndown 3\nup 2\nup 1\ndown 2\nup 3").unwrap();
+        let dive = dive(SimpleSubmarine::default(), &commands);
+        assert_eq!(dive.surfacings, vec![2, 5]);
+    }
+
+    #[test]
+    fn test_surfacing_through_surface() {
+        let commands = generator("down 2\nup 2\nup 1").unwrap();
+        let dive = dive(SimpleSubmarine::default(), &commands);
+        assert_eq!(dive.final_position().depth, -1);
+        assert_eq!(dive.surfacings, vec![1]);
+    }
+
+    #[test]
+    fn test_invalid_commands() {
+        assert!(generator("forward 5\nsideways 3").is_err());
+        assert!(generator("forward five").is_err());
+        assert!(generator("repeat 2\nforward 1").is_err());
+        assert!(generator("end").is_err());
+        assert!(generator("wait 5").is_err());
+    }
 }
@@ -0,0 +1,233 @@
+use std::fmt::Debug;
+
+use aoc_2021::{AocMap, Point};
+use aoc_runner_derive::{aoc, aoc_generator};
+use itertools::iproduct;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Pixel {
+    Lit,
+    Dark,
+}
+
+impl Pixel {
+    fn parse(c: char) -> Option<Self> {
+        match c {
+            '#' => Some(Self::Lit),
+            '.' => Some(Self::Dark),
+            _ => None,
+        }
+    }
+}
+
+impl Debug for Pixel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Lit => write!(f, "#"),
+            Self::Dark => write!(f, "."),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Image {
+    pixels: AocMap<Pixel>,
+    // The value of every pixel outside of `pixels`, all the way out to infinity
+    background: Pixel,
+}
+
+impl Debug for Image {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.render(0))
+    }
+}
+
+impl Image {
+    fn get(&self, x: isize, y: isize) -> Pixel {
+        if x < 0 || y < 0 || x as usize >= self.pixels.size.x || y as usize >= self.pixels.size.y {
+            self.background
+        } else {
+            self.pixels.get(Point::new(x as usize, y as usize))
+        }
+    }
+
+    // Apply the algorithm once. The image grows by one pixel on each side, since that's as far as the
+    // non-background pixels can influence their surroundings.
+    pub fn enhance(&self, algorithm: &[Pixel]) -> Self {
+        let size = Point::new(self.pixels.size.x + 2, self.pixels.size.y + 2);
+        let values = iproduct!(0..size.y as isize, 0..size.x as isize)
+            .map(|(y, x)| {
+                // Coordinates in the new image are offset by one from the old image
+                let index = iproduct!(y - 2..=y, x - 2..=x).fold(0, |index, (y, x)| {
+                    (index << 1) | (self.get(x, y) == Pixel::Lit) as usize
+                });
+                algorithm[index]
+            })
+            .collect();
+
+        // Every background pixel is surrounded by background pixels, so they all map to either the first or
+        // the last entry of the algorithm
+        let background = match self.background {
+            Pixel::Dark => algorithm[0],
+            Pixel::Lit => algorithm[algorithm.len() - 1],
+        };
+
+        Self {
+            pixels: AocMap { size, values },
+            background,
+        }
+    }
+
+    // Every successive enhancement of this image, starting with the first one
+    pub fn enhancements<'a>(&self, algorithm: &'a [Pixel]) -> impl Iterator<Item = Self> + 'a {
+        std::iter::successors(Some(self.enhance(algorithm)), move |image| {
+            Some(image.enhance(algorithm))
+        })
+    }
+
+    // Number of lit pixels, or None if the infinite background is lit
+    pub fn count_lit(&self) -> Option<usize> {
+        if self.background == Pixel::Lit {
+            None
+        } else {
+            Some(
+                self.pixels
+                    .values
+                    .iter()
+                    .filter(|p| **p == Pixel::Lit)
+                    .count(),
+            )
+        }
+    }
+
+    // Render the image surrounded by `padding` pixels of background on each side
+    pub fn render(&self, padding: usize) -> String {
+        let padding = padding as isize;
+        let (width, height) = (self.pixels.size.x as isize, self.pixels.size.y as isize);
+        (-padding..height + padding)
+            .map(|y| {
+                (-padding..width + padding)
+                    .map(|x| format!("{:?}", self.get(x, y)))
+                    .collect::<String>()
+                    + "\n"
+            })
+            .collect()
+    }
+}
+
+fn enhance_and_count(algorithm: &[Pixel], image: &Image, steps: usize) -> Option<usize> {
+    if steps == 0 {
+        return image.count_lit();
+    }
+
+    image.enhancements(algorithm).nth(steps - 1)?.count_lit()
+}
+
+#[aoc_generator(day20)]
+pub fn generator(input: &str) -> (Vec<Pixel>, Image) {
+    let (algorithm, image) = input
+        .split_once("\n\n")
+        .expect("Missing blank line between algorithm and image");
+    let algorithm = algorithm
+        .chars()
+        .filter_map(Pixel::parse)
+        .collect::<Vec<_>>();
+    assert_eq!(algorithm.len(), 512, "Algorithm must have 512 entries");
+
+    let image = Image {
+        pixels: AocMap::from_render(image, Pixel::parse),
+        background: Pixel::Dark,
+    };
+    (algorithm, image)
+}
+
+#[aoc(day20, part1)]
+pub fn part1((algorithm, image): &(Vec<Pixel>, Image)) -> Option<usize> {
+    enhance_and_count(algorithm, image, 2)
+}
+
+#[aoc(day20, part2)]
+pub fn part2((algorithm, image): &(Vec<Pixel>, Image)) -> Option<usize> {
+    enhance_and_count(algorithm, image, 50)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The example from the puzzle. The algorithm is a single line, only split up here to fit
+    const INPUT: &str = "..#.#..#####.#.#.#.###.##.....###.##.#..###.####..#####..#....#.\
+                         .#..##..###..######.###...####..#..#####..##..#.#####...##.#.#..\
+                         #.##..#.#......#.###.######.###.####...#.##.##..#..#..#####.....\
+                         #.#....###..#.##......#.....#..#..#..##..#...##.######.####.####\
+                         .#.#...#.......#..#.#.#...####.##.#......#..#...##.#.##..#...##.\
+                         #.##..###.#......#.#.......#.#.#.####.###.##...#.....####.#..#..\
+                         #.##.#....##..#.####....##...##..#...#......#.#.......#.......##\
+                         ..####..#...#.#.#...##..#.#..###..#####........#..####......#..#\n\n\
+                         #..#.\n\
+                         #....\n\
+                         ##..#\n\
+                         ..#..\n\
+                         ..###";
+
+    const IMAGE: &str = "#..#.\n\
+                         #....\n\
+                         ##..#\n\
+                         ..#..\n\
+                         ..###";
+
+    // Build an algorithm by evaluating `rule` on every 3x3 neighborhood index
+    fn algorithm<F: Fn(usize) -> bool>(rule: F) -> String {
+        (0..512).map(|i| if rule(i) { '#' } else { '.' }).collect()
+    }
+
+    #[test]
+    fn test_identity() {
+        // Only the center pixel (bit 4) determines the output
+        let input = format!("{}\n\n{}", algorithm(|i| i & 0b10000 != 0), IMAGE);
+        let (algorithm, image) = generator(&input);
+        let enhanced = image.enhancements(&algorithm).nth(2).unwrap();
+        assert_eq!(enhanced.render(0), image.render(3));
+        assert_eq!(enhance_and_count(&algorithm, &image, 3), Some(10));
+    }
+
+    #[test]
+    fn test_dilation() {
+        // A pixel is lit if anything in its neighborhood was
+        let input = format!("{}\n\n.....\n..#..\n.....", algorithm(|i| i != 0));
+        let (algorithm, image) = generator(&input);
+        let mut steps = image.enhancements(&algorithm);
+        assert_eq!(
+            steps.next().unwrap().render(0),
+            ".......\n\
+             ..###..\n\
+             ..###..\n\
+             ..###..\n\
+             .......\n"
+        );
+        assert_eq!(steps.next().unwrap().count_lit(), Some(25));
+    }
+
+    #[test]
+    fn test_flickering_background() {
+        // Dark neighborhoods turn lit and lit neighborhoods turn dark, so the background flips every step
+        let input = format!("{}\n\n{}", algorithm(|i| i != 511), IMAGE);
+        let (algorithm, image) = generator(&input);
+        let mut steps = image.enhancements(&algorithm);
+        let first = steps.next().unwrap();
+        assert_eq!(first.count_lit(), None);
+        assert_eq!(&first.render(1)[..10], "#########\n");
+        assert!(steps.next().unwrap().count_lit().is_some());
+        assert_eq!(enhance_and_count(&algorithm, &image, 2), Some(0));
+    }
+
+    #[test]
+    fn test_part1() {
+        assert_eq!(part1(&generator(INPUT)), Some(35));
+    }
+
+    #[test]
+    fn test_part2() {
+        assert_eq!(part2(&generator(INPUT)), Some(3351));
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+
+use aoc_runner_derive::{aoc, aoc_generator};
+use itertools::Itertools;
+
+const ROLLS_PER_TURN: usize = 3;
+
+#[derive(Debug, Copy, Clone)]
+pub struct Rules {
+    // Positions go from 1 to `board_size`, inclusive
+    pub board_size: u32,
+    pub winning_score: u32,
+    // The die rolls values from 1 to `die_faces`, inclusive
+    pub die_faces: u32,
+}
+
+impl Rules {
+    pub const DETERMINISTIC: Self = Self {
+        board_size: 10,
+        winning_score: 1000,
+        die_faces: 100,
+    };
+    pub const DIRAC: Self = Self {
+        board_size: 10,
+        winning_score: 21,
+        die_faces: 3,
+    };
+
+    fn advance(&self, position: u32, steps: u32) -> u32 {
+        (position - 1 + steps) % self.board_size + 1
+    }
+
+    // Each possible total of a turn's rolls, along with how many of the die outcomes add up to it
+    fn roll_sums(&self) -> Vec<(u32, u64)> {
+        itertools::repeat_n(1..=self.die_faces, ROLLS_PER_TURN)
+            .multi_cartesian_product()
+            .map(|rolls| rolls.into_iter().sum::<u32>())
+            .counts()
+            .into_iter()
+            .map(|(sum, count)| (sum, count as u64))
+            .sorted()
+            .collect()
+    }
+}
+
+// Play with a die that rolls 1, 2, 3, ... wrapping around after `die_faces`. Returns the losing player's
+// score multiplied by the number of times the die was rolled.
+pub fn play_deterministic(start: [u32; 2], rules: &Rules) -> u64 {
+    let mut positions = start;
+    let mut scores = [0u32; 2];
+    let mut die = (1..=rules.die_faces).cycle();
+    let mut num_rolls = 0u64;
+
+    for player in (0..2).cycle() {
+        let steps = die.by_ref().take(ROLLS_PER_TURN).sum::<u32>();
+        num_rolls += ROLLS_PER_TURN as u64;
+        positions[player] = rules.advance(positions[player], steps);
+        scores[player] += positions[player];
+        if scores[player] >= rules.winning_score {
+            return scores[1 - player] as u64 * num_rolls;
+        }
+    }
+
+    unreachable!()
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct GameState {
+    positions: [u32; 2],
+    scores: [u32; 2],
+    turn: usize,
+}
+
+struct MultiverseCounter {
+    rules: Rules,
+    roll_sums: Vec<(u32, u64)>,
+    // Number of universes in which each player wins, starting from a given state
+    memo: HashMap<GameState, [u64; 2]>,
+}
+
+impl MultiverseCounter {
+    fn count_wins(&mut self, state: GameState) -> [u64; 2] {
+        if let Some(wins) = self.memo.get(&state) {
+            return *wins;
+        }
+
+        let mut wins = [0u64; 2];
+        for (steps, multiplicity) in self.roll_sums.clone() {
+            let player = state.turn;
+            let mut next = state;
+            next.positions[player] = self.rules.advance(state.positions[player], steps);
+            next.scores[player] += next.positions[player];
+            if next.scores[player] >= self.rules.winning_score {
+                wins[player] += multiplicity;
+            } else {
+                next.turn = 1 - player;
+                let next_wins = self.count_wins(next);
+                wins[0] += multiplicity * next_wins[0];
+                wins[1] += multiplicity * next_wins[1];
+            }
+        }
+
+        self.memo.insert(state, wins);
+        wins
+    }
+}
+
+// Play with a die that splits the universe on every roll, returning how many universes each player wins in
+pub fn count_universes(start: [u32; 2], rules: &Rules) -> [u64; 2] {
+    let mut counter = MultiverseCounter {
+        rules: *rules,
+        roll_sums: rules.roll_sums(),
+        memo: HashMap::new(),
+    };
+    counter.count_wins(GameState {
+        positions: start,
+        scores: [0, 0],
+        turn: 0,
+    })
+}
+
+#[aoc_generator(day21)]
+pub fn generator(input: &str) -> [u32; 2] {
+    let (player1, player2) = input
+        .lines()
+        .filter_map(|line| line.rsplit(' ').next())
+        .map(|position| position.trim().parse::<u32>().unwrap())
+        .collect_tuple()
+        .expect("Expected exactly two starting positions");
+    [player1, player2]
+}
+
+#[aoc(day21, part1)]
+pub fn part1(start: &[u32; 2]) -> u64 {
+    play_deterministic(*start, &Rules::DETERMINISTIC)
+}
+
+#[aoc(day21, part2)]
+pub fn part2(start: &[u32; 2]) -> u64 {
+    let wins = count_universes(*start, &Rules::DIRAC);
+    wins[0].max(wins[1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "Player 1 starting position: 4\n\
+                         Player 2 starting position: 8";
+
+    #[test]
+    fn test_part1() {
+        assert_eq!(part1(&generator(INPUT)), 739785);
+    }
+
+    #[test]
+    fn test_part2() {
+        assert_eq!(
+            count_universes(generator(INPUT), &Rules::DIRAC),
+            [444356092776315, 341960390180808]
+        );
+    }
+
+    // Count wins by expanding every single roll, without merging equal sums or memoizing
+    fn brute_force(rules: &Rules, positions: [u32; 2], scores: [u32; 2], turn: usize) -> [u64; 2] {
+        let mut wins = [0u64; 2];
+        for rolls in
+            itertools::repeat_n(1..=rules.die_faces, ROLLS_PER_TURN).multi_cartesian_product()
+        {
+            let (mut positions, mut scores) = (positions, scores);
+            positions[turn] = rules.advance(positions[turn], rolls.iter().sum());
+            scores[turn] += positions[turn];
+            if scores[turn] >= rules.winning_score {
+                wins[turn] += 1;
+            } else {
+                let next = brute_force(rules, positions, scores, 1 - turn);
+                wins[0] += next[0];
+                wins[1] += next[1];
+            }
+        }
+        wins
+    }
+
+    #[test]
+    fn test_variant() {
+        let rules = Rules {
+            board_size: 7,
+            winning_score: 10,
+            die_faces: 2,
+        };
+        assert_eq!(rules.roll_sums(), vec![(3, 1), (4, 3), (5, 3), (6, 1)]);
+        for start in [[1, 1], [3, 6], [7, 2]] {
+            assert_eq!(
+                count_universes(start, &rules),
+                brute_force(&rules, start, [0, 0], 0)
+            );
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use aoc_2021::Point3;
+use aoc_runner_derive::{aoc, aoc_generator};
+use itertools::Itertools;
+
+// An axis-aligned box of cubes, with both `min` and `max` inclusive
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Cuboid {
+    pub min: Point3,
+    pub max: Point3,
+}
+
+impl Cuboid {
+    pub const fn new(min: Point3, max: Point3) -> Self {
+        Self { min, max }
+    }
+
+    pub fn volume(&self) -> i64 {
+        (self.max.x - self.min.x + 1) as i64
+            * (self.max.y - self.min.y + 1) as i64
+            * (self.max.z - self.min.z + 1) as i64
+    }
+
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let min = Point3::new(
+            self.min.x.max(other.min.x),
+            self.min.y.max(other.min.y),
+            self.min.z.max(other.min.z),
+        );
+        let max = Point3::new(
+            self.max.x.min(other.max.x),
+            self.max.y.min(other.max.y),
+            self.max.z.min(other.max.z),
+        );
+
+        if min.x > max.x || min.y > max.y || min.z > max.z {
+            None
+        } else {
+            Some(Self::new(min, max))
+        }
+    }
+
+    // The parts of this cuboid that are not in `other`, as up to six non-overlapping cuboids
+    pub fn subtract(&self, other: &Self) -> Vec<Self> {
+        let overlap = match self.intersection(other) {
+            Some(overlap) => overlap,
+            None => return vec![*self],
+        };
+
+        let mut pieces = Vec::new();
+        let mut remainder = *self;
+        // Slice off the slabs on either side of the overlap one axis at a time, shrinking the remainder down
+        // to the overlap itself
+        if remainder.min.x < overlap.min.x {
+            let mut slab = remainder;
+            slab.max.x = overlap.min.x - 1;
+            pieces.push(slab);
+            remainder.min.x = overlap.min.x;
+        }
+        if remainder.max.x > overlap.max.x {
+            let mut slab = remainder;
+            slab.min.x = overlap.max.x + 1;
+            pieces.push(slab);
+            remainder.max.x = overlap.max.x;
+        }
+        if remainder.min.y < overlap.min.y {
+            let mut slab = remainder;
+            slab.max.y = overlap.min.y - 1;
+            pieces.push(slab);
+            remainder.min.y = overlap.min.y;
+        }
+        if remainder.max.y > overlap.max.y {
+            let mut slab = remainder;
+            slab.min.y = overlap.max.y + 1;
+            pieces.push(slab);
+            remainder.max.y = overlap.max.y;
+        }
+        if remainder.min.z < overlap.min.z {
+            let mut slab = remainder;
+            slab.max.z = overlap.min.z - 1;
+            pieces.push(slab);
+        }
+        if remainder.max.z > overlap.max.z {
+            let mut slab = remainder;
+            slab.min.z = overlap.max.z + 1;
+            pieces.push(slab);
+        }
+
+        pieces
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct RebootStep {
+    pub on: bool,
+    pub cuboid: Cuboid,
+}
+
+impl RebootStep {
+    fn parse(line: &str) -> Option<Self> {
+        let (state, ranges) = line.split_once(' ')?;
+        let on = match state {
+            "on" => true,
+            "off" => false,
+            _ => return None,
+        };
+
+        let ((x_min, x_max), (y_min, y_max), (z_min, z_max)) = ranges
+            .split(',')
+            .map(|range| {
+                let (min, max) = range.get(2..)?.split_once("..")?;
+                Some((min.parse::<i32>().ok()?, max.parse::<i32>().ok()?))
+            })
+            .collect::<Option<Vec<_>>>()?
+            .into_iter()
+            .collect_tuple()?;
+
+        Some(Self {
+            on,
+            cuboid: Cuboid::new(
+                Point3::new(x_min, y_min, z_min),
+                Point3::new(x_max, y_max, z_max),
+            ),
+        })
+    }
+}
+
+// Clip every step to `region`, dropping the ones that fall entirely outside of it
+fn restrict<'a>(
+    steps: &'a [RebootStep],
+    region: Option<&'a Cuboid>,
+) -> impl Iterator<Item = RebootStep> + 'a {
+    steps.iter().filter_map(move |step| match region {
+        Some(region) => step.cuboid.intersection(region).map(|cuboid| RebootStep {
+            on: step.on,
+            cuboid,
+        }),
+        None => Some(*step),
+    })
+}
+
+// Count the lit cubes by keeping a list of signed cuboids: every new step cancels out its overlap with each
+// existing cuboid, and "on" steps then add themselves
+pub fn count_lit(steps: &[RebootStep], region: Option<&Cuboid>) -> i64 {
+    let mut signed_cuboids: Vec<(Cuboid, i64)> = Vec::new();
+    for step in restrict(steps, region) {
+        let corrections = signed_cuboids
+            .iter()
+            .filter_map(|(cuboid, sign)| {
+                cuboid
+                    .intersection(&step.cuboid)
+                    .map(|overlap| (overlap, -sign))
+            })
+            .collect_vec();
+        signed_cuboids.extend(corrections);
+        if step.on {
+            signed_cuboids.push((step.cuboid, 1));
+        }
+    }
+
+    signed_cuboids
+        .iter()
+        .map(|(cuboid, sign)| cuboid.volume() * sign)
+        .sum()
+}
+
+// Count the lit cubes by keeping a list of disjoint lit cuboids, splitting them whenever a step overlaps them
+pub fn count_lit_by_splitting(steps: &[RebootStep], region: Option<&Cuboid>) -> i64 {
+    let mut lit: Vec<Cuboid> = Vec::new();
+    for step in restrict(steps, region) {
+        lit = lit
+            .iter()
+            .flat_map(|cuboid| cuboid.subtract(&step.cuboid))
+            .collect();
+        if step.on {
+            lit.push(step.cuboid);
+        }
+    }
+
+    lit.iter().map(Cuboid::volume).sum()
+}
+
+const INITIALIZATION_REGION: Cuboid =
+    Cuboid::new(Point3::new(-50, -50, -50), Point3::new(50, 50, 50));
+
+#[aoc_generator(day22)]
+pub fn generator(input: &str) -> Vec<RebootStep> {
+    input
+        .lines()
+        .map(|line| {
+            RebootStep::parse(line).unwrap_or_else(|| panic!("Invalid reboot step: {}", line))
+        })
+        .collect()
+}
+
+#[aoc(day22, part1)]
+pub fn part1(steps: &[RebootStep]) -> i64 {
+    count_lit(steps, Some(&INITIALIZATION_REGION))
+}
+
+#[aoc(day22, part2)]
+pub fn part2(steps: &[RebootStep]) -> i64 {
+    count_lit(steps, None)
+}
+
+#[aoc(day22, part2, Splitting)]
+pub fn part2_splitting(steps: &[RebootStep]) -> i64 {
+    count_lit_by_splitting(steps, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashSet;
+
+    const INPUT: &str = "on x=10..12,y=10..12,z=10..12\n\
+                         on x=11..13,y=11..13,z=11..13\n\
+                         off x=9..11,y=9..11,z=9..11\n\
+                         on x=10..10,y=10..10,z=10..10";
+
+    #[test]
+    fn test_part1() {
+        assert_eq!(part1(&generator(INPUT)), 39);
+    }
+
+    #[test]
+    fn test_subtract() {
+        let a = Cuboid::new(Point3::new(0, 0, 0), Point3::new(9, 9, 9));
+        let b = Cuboid::new(Point3::new(3, 3, 3), Point3::new(5, 5, 20));
+        let pieces = a.subtract(&b);
+        assert_eq!(pieces.len(), 5);
+        assert_eq!(
+            pieces.iter().map(Cuboid::volume).sum::<i64>(),
+            1000 - 3 * 3 * 7
+        );
+        for (p, q) in pieces.iter().tuple_combinations() {
+            assert_eq!(p.intersection(q), None);
+        }
+        assert!(pieces.iter().all(|p| p.intersection(&b).is_none()));
+    }
+
+    #[test]
+    fn test_against_brute_force() {
+        // Pseudo-random steps in a small volume, so that we can also just toggle every individual cube
+        let mut seed = 22u32;
+        let mut next = |range: i32| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            (seed >> 16) as i32 % range
+        };
+        let steps = (0..30)
+            .map(|_| {
+                let min = Point3::new(next(20) - 10, next(20) - 10, next(20) - 10);
+                let size = Point3::new(next(8), next(8), next(8));
+                RebootStep {
+                    on: next(3) != 0,
+                    cuboid: Cuboid::new(min, min + size),
+                }
+            })
+            .collect_vec();
+
+        let region = Cuboid::new(Point3::new(-5, -5, -5), Point3::new(5, 5, 5));
+        let mut lit = HashSet::new();
+        let mut lit_in_region = HashSet::new();
+        for step in &steps {
+            let (min, max) = (step.cuboid.min, step.cuboid.max);
+            for cube in itertools::iproduct!(min.x..=max.x, min.y..=max.y, min.z..=max.z) {
+                let in_region = [cube.0, cube.1, cube.2].iter().all(|c| c.abs() <= 5);
+                if step.on {
+                    lit.insert(cube);
+                    if in_region {
+                        lit_in_region.insert(cube);
+                    }
+                } else {
+                    lit.remove(&cube);
+                    lit_in_region.remove(&cube);
+                }
+            }
+        }
+
+        assert_eq!(count_lit(&steps, None), lit.len() as i64);
+        assert_eq!(count_lit_by_splitting(&steps, None), lit.len() as i64);
+        assert_eq!(count_lit(&steps, Some(&region)), lit_in_region.len() as i64);
+        assert_eq!(
+            count_lit_by_splitting(&steps, Some(&region)),
+            lit_in_region.len() as i64
+        );
+    }
+}
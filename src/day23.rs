@@ -0,0 +1,324 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt::Display;
+
+use aoc_runner_derive::aoc;
+use itertools::Itertools;
+
+const HALLWAY_LENGTH: usize = 11;
+const NUM_ROOMS: usize = 4;
+const MAX_DEPTH: usize = 4;
+
+// Lines that get folded out of the diagram for the second part of the puzzle
+const UNFOLDED_LINES: [&str; 2] = ["  #D#C#B#A#", "  #D#B#A#C#"];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Amphipod {
+    Amber,
+    Bronze,
+    Copper,
+    Desert,
+}
+
+impl Amphipod {
+    fn parse(c: char) -> Option<Self> {
+        match c {
+            'A' => Some(Self::Amber),
+            'B' => Some(Self::Bronze),
+            'C' => Some(Self::Copper),
+            'D' => Some(Self::Desert),
+            _ => None,
+        }
+    }
+
+    fn energy_per_step(&self) -> u32 {
+        match self {
+            Self::Amber => 1,
+            Self::Bronze => 10,
+            Self::Copper => 100,
+            Self::Desert => 1000,
+        }
+    }
+
+    fn target_room(&self) -> usize {
+        *self as usize
+    }
+
+    fn render(cell: Option<Self>) -> char {
+        match cell {
+            Some(Self::Amber) => 'A',
+            Some(Self::Bronze) => 'B',
+            Some(Self::Copper) => 'C',
+            Some(Self::Desert) => 'D',
+            None => '.',
+        }
+    }
+}
+
+// Hallway position right outside the given room, where amphipods never stop
+fn room_entrance(room: usize) -> usize {
+    2 + 2 * room
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Burrow {
+    hallway: [Option<Amphipod>; HALLWAY_LENGTH],
+    // `rooms[room][0]` is the spot closest to the hallway, and only the first `depth` spots are used
+    rooms: [[Option<Amphipod>; MAX_DEPTH]; NUM_ROOMS],
+    depth: usize,
+}
+
+impl Burrow {
+    fn parse(input: &str) -> Option<Self> {
+        let mut lines = input.lines().skip(1);
+        let mut hallway = [None; HALLWAY_LENGTH];
+        for (spot, c) in hallway.iter_mut().zip(lines.next()?.chars().skip(1)) {
+            *spot = Amphipod::parse(c);
+        }
+
+        let mut rooms = [[None; MAX_DEPTH]; NUM_ROOMS];
+        let mut depth = 0;
+        for line in lines.filter(|line| line.chars().any(|c| c.is_ascii_alphabetic())) {
+            if depth == MAX_DEPTH {
+                return None;
+            }
+            let chars = line.chars().collect_vec();
+            for (room, spots) in rooms.iter_mut().enumerate() {
+                spots[depth] = Amphipod::parse(*chars.get(3 + 2 * room)?);
+            }
+            depth += 1;
+        }
+
+        Some(Self {
+            hallway,
+            rooms,
+            depth,
+        })
+    }
+
+    fn is_organized(&self) -> bool {
+        self.rooms.iter().enumerate().all(|(room, spots)| {
+            spots[..self.depth]
+                .iter()
+                .all(|spot| spot.map(|a| a.target_room()) == Some(room))
+        })
+    }
+
+    // Whether the room only contains amphipods that belong in it, so others may move in
+    fn is_accepting(&self, room: usize) -> bool {
+        self.rooms[room][..self.depth]
+            .iter()
+            .flatten()
+            .all(|a| a.target_room() == room)
+    }
+
+    // Whether every hallway spot after `from` up to and including `to` is free
+    fn hallway_clear(&self, from: usize, to: usize) -> bool {
+        let path = if from < to {
+            from + 1..=to
+        } else {
+            to..=from - 1
+        };
+        self.hallway[path].iter().all(Option::is_none)
+    }
+
+    // Deepest free spot in a room, assuming the room is accepting
+    fn free_spot(&self, room: usize) -> Option<usize> {
+        self.rooms[room][..self.depth]
+            .iter()
+            .rposition(Option::is_none)
+    }
+
+    // Every state reachable in a single move, along with the energy that move costs
+    fn moves(&self) -> Vec<(u32, Self)> {
+        let mut moves = Vec::new();
+
+        // Amphipods in the hallway can only move into their own room
+        for (position, amphipod) in self.hallway.iter().enumerate() {
+            let amphipod = match amphipod {
+                Some(amphipod) => *amphipod,
+                None => continue,
+            };
+            let room = amphipod.target_room();
+            let entrance = room_entrance(room);
+            if !self.is_accepting(room) || !self.hallway_clear(position, entrance) {
+                continue;
+            }
+            if let Some(spot) = self.free_spot(room) {
+                let mut next = *self;
+                next.hallway[position] = None;
+                next.rooms[room][spot] = Some(amphipod);
+                let steps = position.abs_diff(entrance) + spot + 1;
+                moves.push((steps as u32 * amphipod.energy_per_step(), next));
+            }
+        }
+
+        // The topmost amphipod in a room can move out into the hallway, or straight into its own room
+        for room in 0..NUM_ROOMS {
+            if self.is_accepting(room) {
+                continue;
+            }
+            let (spot, amphipod) = match self.rooms[room][..self.depth]
+                .iter()
+                .enumerate()
+                .find_map(|(spot, a)| a.map(|a| (spot, a)))
+            {
+                Some(top) => top,
+                None => continue,
+            };
+            let entrance = room_entrance(room);
+
+            let target = amphipod.target_room();
+            let target_entrance = room_entrance(target);
+            if self.is_accepting(target) && self.hallway_clear(entrance, target_entrance) {
+                if let Some(target_spot) = self.free_spot(target) {
+                    let mut next = *self;
+                    next.rooms[room][spot] = None;
+                    next.rooms[target][target_spot] = Some(amphipod);
+                    let steps = spot + 1 + entrance.abs_diff(target_entrance) + target_spot + 1;
+                    moves.push((steps as u32 * amphipod.energy_per_step(), next));
+                    continue;
+                }
+            }
+
+            for position in 0..HALLWAY_LENGTH {
+                if (0..NUM_ROOMS).any(|r| room_entrance(r) == position)
+                    || !self.hallway_clear(entrance, position)
+                {
+                    continue;
+                }
+                let mut next = *self;
+                next.rooms[room][spot] = None;
+                next.hallway[position] = Some(amphipod);
+                let steps = spot + 1 + entrance.abs_diff(position);
+                moves.push((steps as u32 * amphipod.energy_per_step(), next));
+            }
+        }
+
+        moves
+    }
+}
+
+impl Display for Burrow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "#############")?;
+        writeln!(
+            f,
+            "#{}#",
+            self.hallway.iter().copied().map(Amphipod::render).join("")
+        )?;
+        for depth in 0..self.depth {
+            let spots = self
+                .rooms
+                .iter()
+                .map(|spots| Amphipod::render(spots[depth]))
+                .join("#");
+            if depth == 0 {
+                writeln!(f, "###{}###", spots)?;
+            } else {
+                writeln!(f, "  #{}#", spots)?;
+            }
+        }
+        writeln!(f, "  #########")
+    }
+}
+
+pub struct Organization {
+    pub energy: u32,
+    // The burrow after each move, ending with the organized burrow
+    pub steps: Vec<Burrow>,
+}
+
+impl Display for Organization {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for step in &self.steps {
+            writeln!(f, "{}", step)?;
+        }
+        writeln!(f, "Total energy: {}", self.energy)
+    }
+}
+
+// Dijkstra's over burrow states, returning the cheapest way to get every amphipod into its room
+pub fn organize(start: &Burrow) -> Option<Organization> {
+    let mut best: HashMap<Burrow, (u32, Option<Burrow>)> = HashMap::from([(*start, (0, None))]);
+    let mut queue = BinaryHeap::from([Reverse((0, *start))]);
+
+    while let Some(Reverse((energy, burrow))) = queue.pop() {
+        if burrow.is_organized() {
+            let mut steps = vec![burrow];
+            while let Some((_, Some(previous))) = best.get(steps.last().unwrap()) {
+                steps.push(*previous);
+            }
+            steps.pop();
+            steps.reverse();
+            return Some(Organization { energy, steps });
+        }
+
+        if best[&burrow].0 < energy {
+            continue;
+        }
+
+        for (cost, next) in burrow.moves() {
+            let next_energy = energy + cost;
+            if best
+                .get(&next)
+                .is_none_or(|(known, _)| next_energy < *known)
+            {
+                best.insert(next, (next_energy, Some(burrow)));
+                queue.push(Reverse((next_energy, next)));
+            }
+        }
+    }
+
+    None
+}
+
+#[aoc(day23, part1)]
+pub fn part1(diagram: &str) -> Option<u32> {
+    organize(&Burrow::parse(diagram)?).map(|organization| organization.energy)
+}
+
+#[aoc(day23, part2)]
+pub fn part2(diagram: &str) -> Option<u32> {
+    let mut lines = diagram.lines().collect_vec();
+    lines.splice(3..3, UNFOLDED_LINES);
+    organize(&Burrow::parse(&lines.join("\n"))?).map(|organization| organization.energy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "#############\n\
+                         #...........#\n\
+                         ###B#C#B#D###\n  \
+                           #A#D#C#A#\n  \
+                           #########";
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            Burrow::parse(INPUT).unwrap().to_string(),
+            INPUT.to_owned() + "\n"
+        );
+    }
+
+    #[test]
+    fn test_part1() {
+        let organization = organize(&Burrow::parse(INPUT).unwrap()).unwrap();
+        assert_eq!(organization.energy, 12521);
+        assert_eq!(
+            organization.steps.last().unwrap().to_string(),
+            "#############\n\
+             #...........#\n\
+             ###A#B#C#D###\n  \
+               #A#B#C#D#\n  \
+               #########\n"
+        );
+    }
+
+    #[test]
+    fn test_part2() {
+        assert_eq!(part2(INPUT), Some(44169));
+    }
+}
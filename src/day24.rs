@@ -0,0 +1,458 @@
+use anyhow::{anyhow, bail, Result};
+use aoc_runner_derive::{aoc, aoc_generator};
+use itertools::Itertools;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Register {
+    W,
+    X,
+    Y,
+    Z,
+}
+
+impl Register {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "w" => Some(Self::W),
+            "x" => Some(Self::X),
+            "y" => Some(Self::Y),
+            "z" => Some(Self::Z),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Operand {
+    Register(Register),
+    Value(i64),
+}
+
+impl Operand {
+    fn parse(s: &str) -> Result<Self> {
+        if let Some(register) = Register::parse(s) {
+            Ok(Self::Register(register))
+        } else {
+            Ok(Self::Value(
+                s.parse().map_err(|_| anyhow!("Invalid operand: {}", s))?,
+            ))
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Instruction {
+    Inp(Register),
+    Add(Register, Operand),
+    Mul(Register, Operand),
+    Div(Register, Operand),
+    Mod(Register, Operand),
+    Eql(Register, Operand),
+}
+
+impl Instruction {
+    fn parse(line: &str) -> Result<Self> {
+        let mut parts = line.split_whitespace();
+        let op = parts.next().ok_or_else(|| anyhow!("Empty instruction"))?;
+        let register = parts
+            .next()
+            .and_then(Register::parse)
+            .ok_or_else(|| anyhow!("Missing or invalid register in: {}", line))?;
+        if op == "inp" {
+            return Ok(Self::Inp(register));
+        }
+
+        let operand = Operand::parse(
+            parts
+                .next()
+                .ok_or_else(|| anyhow!("Missing operand in: {}", line))?,
+        )?;
+        match op {
+            "add" => Ok(Self::Add(register, operand)),
+            "mul" => Ok(Self::Mul(register, operand)),
+            "div" => Ok(Self::Div(register, operand)),
+            "mod" => Ok(Self::Mod(register, operand)),
+            "eql" => Ok(Self::Eql(register, operand)),
+            _ => bail!("Unknown instruction: {}", line),
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Alu {
+    registers: [i64; 4],
+}
+
+impl Alu {
+    pub fn get(&self, register: Register) -> i64 {
+        self.registers[register as usize]
+    }
+
+    fn value(&self, operand: Operand) -> i64 {
+        match operand {
+            Operand::Register(register) => self.get(register),
+            Operand::Value(value) => value,
+        }
+    }
+
+    // Run the program from the current state, reading from `inputs` for every `inp`
+    pub fn run(&mut self, program: &[Instruction], inputs: &[i64]) -> Result<()> {
+        let mut inputs = inputs.iter();
+        for instruction in program {
+            let (register, value) = match *instruction {
+                Instruction::Inp(register) => (
+                    register,
+                    *inputs.next().ok_or_else(|| anyhow!("Ran out of inputs"))?,
+                ),
+                Instruction::Add(register, operand) => {
+                    (register, self.get(register) + self.value(operand))
+                }
+                Instruction::Mul(register, operand) => {
+                    (register, self.get(register) * self.value(operand))
+                }
+                Instruction::Div(register, operand) => {
+                    let divisor = self.value(operand);
+                    if divisor == 0 {
+                        bail!("Division by zero: {:?}", instruction);
+                    }
+                    (register, self.get(register) / divisor)
+                }
+                Instruction::Mod(register, operand) => {
+                    let (a, b) = (self.get(register), self.value(operand));
+                    if a < 0 || b <= 0 {
+                        bail!("Invalid modulo of {} by {}: {:?}", a, b, instruction);
+                    }
+                    (register, a % b)
+                }
+                Instruction::Eql(register, operand) => {
+                    (register, (self.get(register) == self.value(operand)) as i64)
+                }
+            };
+            self.registers[register as usize] = value;
+        }
+
+        Ok(())
+    }
+}
+
+// An inclusive range of values that a register is known to stay within
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Interval {
+    pub min: i64,
+    pub max: i64,
+}
+
+impl Interval {
+    pub const fn new(min: i64, max: i64) -> Self {
+        Self { min, max }
+    }
+
+    pub fn contains(&self, value: i64) -> bool {
+        self.min <= value && value <= self.max
+    }
+
+    // Smallest interval containing every value of `f` applied to the corners of the two intervals, which
+    // covers all results as long as `f` is monotonic in each argument
+    fn from_corners<F: Fn(i64, i64) -> i64>(a: Self, b: Self, f: F) -> Self {
+        let corners = [
+            f(a.min, b.min),
+            f(a.min, b.max),
+            f(a.max, b.min),
+            f(a.max, b.max),
+        ];
+        Self::new(
+            *corners.iter().min().unwrap(),
+            *corners.iter().max().unwrap(),
+        )
+    }
+
+    fn add(self, other: Self) -> Self {
+        Self::new(
+            self.min.saturating_add(other.min),
+            self.max.saturating_add(other.max),
+        )
+    }
+
+    fn mul(self, other: Self) -> Self {
+        Self::from_corners(self, other, i64::saturating_mul)
+    }
+
+    fn div(self, other: Self) -> Self {
+        if other.contains(0) {
+            // Dividing by zero halts the program, so any result comes from a non-zero divisor, and integer
+            // division by a non-zero value never increases the magnitude
+            let magnitude = self.min.saturating_abs().max(self.max.saturating_abs());
+            Self::new(-magnitude, magnitude)
+        } else {
+            Self::from_corners(self, other, |a, b| a / b)
+        }
+    }
+
+    fn modulo(self, other: Self) -> Self {
+        // Only non-negative values modulo positive values are valid, anything else halts the program
+        let max = self.max.min(other.max.saturating_sub(1)).max(0);
+        if self.min >= 0 && self.max < other.min {
+            self
+        } else {
+            Self::new(0, max)
+        }
+    }
+
+    fn eql(self, other: Self) -> Self {
+        if self.min == self.max && other.min == other.max && self.min == other.min {
+            Self::new(1, 1)
+        } else if self.max < other.min || other.max < self.min {
+            Self::new(0, 0)
+        } else {
+            Self::new(0, 1)
+        }
+    }
+}
+
+// Interval analysis of a program where every input is a digit from 1 to 9. Returns the proven range of each
+// register (in W, X, Y, Z order) after each instruction.
+pub fn analyze(program: &[Instruction]) -> Vec<[Interval; 4]> {
+    let mut ranges = [Interval::new(0, 0); 4];
+    program
+        .iter()
+        .map(|instruction| {
+            let operand_range = |operand: Operand| match operand {
+                Operand::Register(register) => ranges[register as usize],
+                Operand::Value(value) => Interval::new(value, value),
+            };
+            let (register, range) = match *instruction {
+                Instruction::Inp(register) => (register, Interval::new(1, 9)),
+                Instruction::Add(register, operand) => (
+                    register,
+                    ranges[register as usize].add(operand_range(operand)),
+                ),
+                Instruction::Mul(register, operand) => (
+                    register,
+                    ranges[register as usize].mul(operand_range(operand)),
+                ),
+                Instruction::Div(register, operand) => (
+                    register,
+                    ranges[register as usize].div(operand_range(operand)),
+                ),
+                Instruction::Mod(register, operand) => (
+                    register,
+                    ranges[register as usize].modulo(operand_range(operand)),
+                ),
+                Instruction::Eql(register, operand) => (
+                    register,
+                    ranges[register as usize].eql(operand_range(operand)),
+                ),
+            };
+            ranges[register as usize] = range;
+            ranges
+        })
+        .collect()
+}
+
+// The three constants that differ between the per-digit blocks of the MONAD program
+#[derive(Debug, Copy, Clone)]
+struct MonadBlock {
+    z_divisor: i64,
+    x_offset: i64,
+    y_offset: i64,
+}
+
+impl MonadBlock {
+    const LENGTH: usize = 18;
+
+    fn extract(block: &[Instruction]) -> Option<Self> {
+        use Instruction::*;
+        use Operand::Value;
+        use Register::*;
+
+        if block.len() != Self::LENGTH {
+            return None;
+        }
+        match (block[4], block[5], block[15]) {
+            (Div(Z, Value(z_divisor)), Add(X, Value(x_offset)), Add(Y, Value(y_offset))) => {
+                Some(Self {
+                    z_divisor,
+                    x_offset,
+                    y_offset,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Preference {
+    Largest,
+    Smallest,
+}
+
+// Every block either pushes `digit + y_offset` onto a base 26 stack in `z`, or pops a value and only avoids
+// pushing again if `digit == popped + x_offset`. An accepted number has to pop everything it pushes, so each
+// popping digit is tied to one pushing digit by a fixed difference.
+fn solve(program: &[Instruction], preference: Preference) -> Option<u64> {
+    // If the analysis can't even find a way for z to end up as 0, there's no point in searching
+    if !analyze(program).last()?[Register::Z as usize].contains(0) {
+        return None;
+    }
+
+    let mut starts = program
+        .iter()
+        .positions(|instruction| matches!(instruction, Instruction::Inp(_)))
+        .collect_vec();
+    starts.push(program.len());
+    let blocks = starts
+        .iter()
+        .tuple_windows()
+        .map(|(start, end)| MonadBlock::extract(&program[*start..*end]))
+        .collect::<Option<Vec<_>>>()?;
+
+    let mut digits = vec![0i64; blocks.len()];
+    let mut stack = Vec::new();
+    for (index, block) in blocks.iter().enumerate() {
+        match block.z_divisor {
+            1 => stack.push((index, block.y_offset)),
+            26 => {
+                let (pushed_index, y_offset) = stack.pop()?;
+                // digits[index] == digits[pushed_index] + difference
+                let difference = y_offset + block.x_offset;
+                let pushed_digit = match preference {
+                    Preference::Largest => 9.min(9 - difference),
+                    Preference::Smallest => 1.max(1 - difference),
+                };
+                digits[pushed_index] = pushed_digit;
+                digits[index] = pushed_digit + difference;
+            }
+            _ => return None,
+        }
+    }
+
+    if !stack.is_empty() || digits.iter().any(|digit| !(1..=9).contains(digit)) {
+        return None;
+    }
+
+    let mut alu = Alu::default();
+    alu.run(program, &digits).ok()?;
+    if alu.get(Register::Z) != 0 {
+        return None;
+    }
+
+    Some(
+        digits
+            .into_iter()
+            .fold(0, |number, digit| number * 10 + digit as u64),
+    )
+}
+
+#[aoc_generator(day24)]
+pub fn generator(input: &str) -> Result<Vec<Instruction>> {
+    input
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(Instruction::parse)
+        .collect()
+}
+
+#[aoc(day24, part1)]
+pub fn part1(program: &[Instruction]) -> Option<u64> {
+    solve(program, Preference::Largest)
+}
+
+#[aoc(day24, part2)]
+pub fn part2(program: &[Instruction]) -> Option<u64> {
+    solve(program, Preference::Smallest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BINARY: &str = "inp w\n\
+                          add z w\n\
+                          mod z 2\n\
+                          div w 2\n\
+                          add y w\n\
+                          mod y 2\n\
+                          div w 2\n\
+                          add x w\n\
+                          mod x 2\n\
+                          div w 2\n\
+                          mod w 2";
+
+    // Build a MONAD-style program out of (z_divisor, x_offset, y_offset) triples
+    fn monad(blocks: &[(i64, i64, i64)]) -> String {
+        blocks
+            .iter()
+            .map(|(z_divisor, x_offset, y_offset)| {
+                format!(
+                    "inp w\nmul x 0\nadd x z\nmod x 26\ndiv z {}\nadd x {}\neql x w\neql x 0\n\
+                     mul y 0\nadd y 25\nmul y x\nadd y 1\nmul z y\nmul y 0\nadd y w\nadd y {}\n\
+                     mul y x\nadd z y",
+                    z_divisor, x_offset, y_offset
+                )
+            })
+            .join("\n")
+    }
+
+    fn accepts(program: &[Instruction], digits: &[i64]) -> bool {
+        let mut alu = Alu::default();
+        alu.run(program, digits).unwrap();
+        alu.get(Register::Z) == 0
+    }
+
+    #[test]
+    fn test_interpreter() {
+        let program = generator(BINARY).unwrap();
+        let mut alu = Alu::default();
+        alu.run(&program, &[13]).unwrap();
+        assert_eq!(
+            [Register::W, Register::X, Register::Y, Register::Z].map(|r| alu.get(r)),
+            [1, 1, 0, 1]
+        );
+
+        let mut alu = Alu::default();
+        assert!(alu.run(&program, &[]).is_err());
+        assert!(generator("div x 0").is_ok());
+        assert!(Alu::default()
+            .run(&generator("div x 0").unwrap(), &[])
+            .is_err());
+        assert!(generator("sub x 1").is_err());
+    }
+
+    #[test]
+    fn test_analyze() {
+        let ranges = analyze(&generator(BINARY).unwrap());
+        // Every bit register can only ever be 0 or 1, while w is left as the top bit of a digit
+        let last = ranges.last().unwrap();
+        assert_eq!(last[Register::X as usize], Interval::new(0, 1));
+        assert_eq!(last[Register::Y as usize], Interval::new(0, 1));
+        assert_eq!(last[Register::Z as usize], Interval::new(0, 1));
+        assert_eq!(last[Register::W as usize], Interval::new(0, 1));
+
+        let ranges = analyze(&generator("inp x\nmul x -3\nadd x 5\neql x 30").unwrap());
+        assert_eq!(ranges[1][Register::X as usize], Interval::new(-27, -3));
+        assert_eq!(ranges[2][Register::X as usize], Interval::new(-22, 2));
+        assert_eq!(ranges[3][Register::X as usize], Interval::new(0, 0));
+    }
+
+    #[test]
+    fn test_solve() {
+        let program =
+            generator(&monad(&[(1, 12, 7), (1, 11, 3), (26, -5, 0), (26, -9, 4)])).unwrap();
+        let accepted = itertools::repeat_n(1..=9, 4)
+            .multi_cartesian_product()
+            .filter(|digits| accepts(&program, digits))
+            .map(|digits| digits.into_iter().fold(0, |n, d| n * 10 + d as u64))
+            .collect_vec();
+
+        assert_eq!(part1(&program), accepted.iter().max().copied());
+        assert_eq!(part2(&program), accepted.iter().min().copied());
+    }
+
+    #[test]
+    fn test_unsolvable() {
+        // The popping block needs a digit 10 higher than the pushing one
+        let program = generator(&monad(&[(1, 12, 7), (26, 3, 0)])).unwrap();
+        assert_eq!(part1(&program), None);
+    }
+}
@@ -0,0 +1,138 @@
+use std::fmt::Debug;
+
+use aoc_2021::{AocMap, Offset};
+use aoc_runner_derive::{aoc, aoc_generator};
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Cell {
+    East,
+    South,
+    Empty,
+}
+
+impl Debug for Cell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::East => write!(f, ">"),
+            Self::South => write!(f, "v"),
+            Self::Empty => write!(f, "."),
+        }
+    }
+}
+
+type SeaFloor = AocMap<Cell>;
+
+trait HerdSimulate: Sized {
+    fn move_herd(&self, herd: Cell, offset: Offset) -> (Self, usize);
+    fn step(&self) -> (Self, usize);
+    fn steps(&self) -> impl Iterator<Item = (Self, usize)>;
+}
+
+impl HerdSimulate for SeaFloor {
+    // Move every member of one herd that faces an empty cell, all at once. Returns the new map and the number
+    // of sea cucumbers that moved.
+    fn move_herd(&self, herd: Cell, offset: Offset) -> (Self, usize) {
+        let mut next = self.clone();
+        let mut num_moved = 0;
+        for p in self.coordinates().filter(|p| self.get(*p) == herd) {
+            let target = self.get_relative_wrapping(p, offset);
+            // Only look at the map from before this move, so that moves happen simultaneously
+            if self.get(target) == Cell::Empty {
+                next.set(target, herd);
+                next.set(p, Cell::Empty);
+                num_moved += 1;
+            }
+        }
+        (next, num_moved)
+    }
+
+    // The east-facing herd moves first, then the south-facing herd moves based on the updated map
+    fn step(&self) -> (Self, usize) {
+        let (map, moved_east) = self.move_herd(Cell::East, Offset::new(1, 0));
+        let (map, moved_south) = map.move_herd(Cell::South, Offset::new(0, 1));
+        (map, moved_east + moved_south)
+    }
+
+    // The map after each step along with how many sea cucumbers moved, starting with the first step
+    fn steps(&self) -> impl Iterator<Item = (Self, usize)> {
+        std::iter::successors(Some(self.step()), |(map, _)| Some(map.step()))
+    }
+}
+
+#[aoc_generator(day25)]
+pub fn generator(input: &str) -> SeaFloor {
+    AocMap::from_render(input, |c| match c {
+        '>' => Some(Cell::East),
+        'v' => Some(Cell::South),
+        '.' => Some(Cell::Empty),
+        _ => None,
+    })
+}
+
+#[aoc(day25, part1)]
+pub fn part1(map: &SeaFloor) -> usize {
+    map.steps()
+        .position(|(_, num_moved)| num_moved == 0)
+        .unwrap()
+        + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "v...>>.vv>\n\
+                         .vv>>.vv..\n\
+                         >>.>v>...v\n\
+                         >>v>>.>.v.\n\
+                         v>v.vv.v..\n\
+                         >.>>..v...\n\
+                         .vv..>.>v.\n\
+                         v.v..>>v.v\n\
+                         ....v..v.>";
+
+    #[test]
+    fn test_steps() {
+        let map = generator(
+            "..........\n\
+             .>v....v..\n\
+             .......>..\n\
+             ..........",
+        );
+        assert_eq!(
+            format!("{:?}", map.steps().next().unwrap().0),
+            "..........\n\
+             .>........\n\
+             ..v....v>.\n\
+             ..........\n"
+        );
+    }
+
+    #[test]
+    fn test_wrapping() {
+        let map = generator(
+            "...>...\n\
+             .......\n\
+             ......>\n\
+             v.....>\n\
+             ......>\n\
+             .......\n\
+             ..vvv..",
+        );
+        assert_eq!(
+            format!("{:?}", map.steps().nth(3).unwrap().0),
+            ">......\n\
+             ..v....\n\
+             ..>.v..\n\
+             .>.v...\n\
+             ...>...\n\
+             .......\n\
+             v......\n"
+        );
+    }
+
+    #[test]
+    fn test_part1() {
+        assert_eq!(part1(&generator(INPUT)), 58);
+    }
+}
@@ -1,143 +1,357 @@
+use anyhow::{bail, Result};
 use aoc_runner_derive::{aoc, aoc_generator};
+use bit_vec::BitVec;
 
-#[aoc_generator(day3)]
-pub fn generator(input: &str) -> Vec<Vec<u8>> {
-    // Convert each line to a vector, each digit of the number is one u8
-    input
-        .lines()
-        .map(|l| {
-            l.chars()
-                .map(|c| match c {
-                    '0' => 0u8,
-                    '1' => 1u8,
-                    _ => panic!("Invalid integer {}", c),
-                })
-                .collect::<Vec<_>>()
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Commonality {
+    MostCommon,
+    LeastCommon,
+}
+
+// Which bit to keep when filtering on a column
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BitCriteria {
+    pub commonality: Commonality,
+    // The bit to keep when both values are equally common
+    pub tie: bool,
+}
+
+impl BitCriteria {
+    pub const OXYGEN_GENERATOR: Self = Self {
+        commonality: Commonality::MostCommon,
+        tie: true,
+    };
+    pub const CO2_SCRUBBER: Self = Self {
+        commonality: Commonality::LeastCommon,
+        tie: false,
+    };
+
+    // The bit to keep given how many rows have that bit set or not
+    pub fn select(&self, num_ones: usize, num_zeros: usize) -> bool {
+        if num_ones == num_zeros {
+            self.tie
+        } else {
+            (num_ones > num_zeros) == (self.commonality == Commonality::MostCommon)
+        }
+    }
+}
+
+// Interpret bits as a big-endian binary number, or None if it doesn't fit in a u64
+pub fn to_number(bits: &BitVec) -> Option<u64> {
+    if bits.len() > u64::BITS as usize {
+        return None;
+    }
+    Some(
+        bits.iter()
+            .fold(0, |number, bit| (number << 1) | bit as u64),
+    )
+}
+
+#[derive(Debug, Clone)]
+pub struct DiagnosticReport {
+    // Every row has the same width, with the most significant bit first
+    rows: Vec<BitVec>,
+    width: usize,
+}
+
+impl DiagnosticReport {
+    // Rows that are narrower than the widest one are left-padded with zeros
+    pub fn parse(input: &str) -> Result<Self> {
+        let lines = input
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+        let width = lines.iter().map(|line| line.len()).max().unwrap_or(0);
+
+        let mut rows = Vec::with_capacity(lines.len());
+        for line in lines {
+            let mut row = BitVec::from_elem(width - line.len(), false);
+            for c in line.chars() {
+                match c {
+                    '0' => row.push(false),
+                    '1' => row.push(true),
+                    _ => bail!("Invalid binary digit '{}' in: {}", c, line),
+                }
+            }
+            rows.push(row);
+        }
+
+        Ok(Self { rows, width })
+    }
+
+    pub fn rows(&self) -> &[BitVec] {
+        &self.rows
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    // Number of set bits in each column of the given rows
+    fn column_counts<'a, I>(rows: I, width: usize) -> Vec<usize>
+    where
+        I: IntoIterator<Item = &'a BitVec>,
+    {
+        rows.into_iter().fold(vec![0; width], |mut counts, row| {
+            for (count, bit) in counts.iter_mut().zip(row) {
+                *count += bit as usize;
+            }
+            counts
+        })
+    }
+
+    // Pick a bit for each column by applying the criteria to the whole report
+    fn column_bits(&self, criteria: &BitCriteria) -> BitVec {
+        let num_rows = self.rows.len();
+        Self::column_counts(&self.rows, self.width)
+            .into_iter()
+            .map(|num_ones| criteria.select(num_ones, num_rows - num_ones))
+            .collect()
+    }
+
+    pub fn gamma_rate(&self) -> BitVec {
+        self.column_bits(&BitCriteria {
+            commonality: Commonality::MostCommon,
+            tie: true,
         })
-        .collect()
+    }
+
+    pub fn epsilon_rate(&self) -> BitVec {
+        self.column_bits(&BitCriteria {
+            commonality: Commonality::LeastCommon,
+            tie: false,
+        })
+    }
+
+    // Narrow the rows down column by column, keeping the rows whose bit matches the one selected by the
+    // criteria, until only one row is left. Duplicate rows can't be told apart, so the first of them wins.
+    pub fn filter_by_bit_criteria(&self, criteria: &BitCriteria) -> Option<&BitVec> {
+        let mut candidates = self.rows.iter().collect::<Vec<_>>();
+        for column in 0..self.width {
+            if candidates.len() <= 1 {
+                break;
+            }
+
+            // Skip columns where every candidate has the same bit, so that we never filter out everything
+            let num_ones = candidates.iter().filter(|row| row[column]).count();
+            let num_zeros = candidates.len() - num_ones;
+            if num_ones > 0 && num_zeros > 0 {
+                let keep = criteria.select(num_ones, num_zeros);
+                candidates.retain(|row| row[column] == keep);
+            }
+        }
+
+        // Every column is down to a single bit by now, so any candidates left are all the same row
+        candidates.first().copied()
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct TrieNode {
+    // Number of rows that start with the bits leading up to this node
+    count: usize,
+    // Indices into `BitTrie::nodes` for the nodes following a 0 and a 1 bit
+    children: [Option<usize>; 2],
+}
+
+// Every row of a report stored as a path from the root, so each filtering step is just a choice between the
+// two children of a node
+#[derive(Debug, Clone)]
+pub struct BitTrie {
+    nodes: Vec<TrieNode>,
+    width: usize,
+}
+
+impl BitTrie {
+    pub fn new(report: &DiagnosticReport) -> Self {
+        let mut nodes = vec![TrieNode::default()];
+        for row in report.rows() {
+            let mut node = 0;
+            nodes[node].count += 1;
+            for bit in row {
+                node = match nodes[node].children[bit as usize] {
+                    Some(child) => child,
+                    None => {
+                        nodes.push(TrieNode::default());
+                        let child = nodes.len() - 1;
+                        nodes[node].children[bit as usize] = Some(child);
+                        child
+                    }
+                };
+                nodes[node].count += 1;
+            }
+        }
+
+        Self {
+            nodes,
+            width: report.width(),
+        }
+    }
+
+    // Same as `DiagnosticReport::filter_by_bit_criteria`, but only visits one node per column
+    pub fn rating(&self, criteria: &BitCriteria) -> Option<BitVec> {
+        let count = |child: Option<usize>| child.map_or(0, |child| self.nodes[child].count);
+
+        let mut rating = BitVec::with_capacity(self.width);
+        let mut node = &self.nodes[0];
+        if node.count == 0 {
+            return None;
+        }
+        for _ in 0..self.width {
+            let [zeros, ones] = node.children;
+            let (num_zeros, num_ones) = (count(zeros), count(ones));
+            // When only one of the bits is present there's nothing to filter, so follow that one
+            let keep = if num_zeros == 0 || num_ones == 0 {
+                num_ones > 0
+            } else {
+                criteria.select(num_ones, num_zeros)
+            };
+            rating.push(keep);
+            node = &self.nodes[node.children[keep as usize]?];
+        }
+
+        Some(rating)
+    }
+}
+
+#[aoc_generator(day3)]
+pub fn generator(input: &str) -> Result<DiagnosticReport> {
+    DiagnosticReport::parse(input)
 }
 
 #[aoc(day3, part1)]
-pub fn part1(binary_numbers: &[Vec<u8>]) -> u32 {
-    // Sum each line (and count the number of lines) into one big Vec<u32>
-    let (num_rows, column_sums) =
-        binary_numbers
-            .iter()
-            .fold((0u32, vec![0u32; 0]), |(count, accum), row| {
-                // Length of the longest previous number in the sequence
-                let accumulated_number_bitlength = accum.len();
-                // Make sure that they are the same size -- left pad accum if it's not as long as this row
-                let accum = std::iter::repeat(0)
-                    .take(row.len().saturating_sub(accumulated_number_bitlength))
-                    .chain(accum);
-
-                // Make sure that they are the same size -- left pad row if it's not as long as the previous rows
-                let row = std::iter::repeat(&0u8)
-                    .take(accumulated_number_bitlength.saturating_sub(row.len()))
-                    .chain(row);
-
-                // Join the two iterators into tuples ala ((it1[0], it2[0]), (it1[1], it2[1]), ...) and then add
-                // them elementwise (it1[0] + it2[0], it1[1] + it2[1], ..).
-                let accum = accum
-                    .zip(row)
-                    .map(|(a, r)| a + (*r as u32))
-                    .collect::<Vec<_>>();
-
-                return (count + 1, accum);
-            });
-
-    let binary_number_length = column_sums.len();
-    // This is a mask whose bits is 1 for the length of our binary numbers
-    let number_mask = (1 << binary_number_length) - 1;
-    // Calculate epsilon by creating a number that is 1 for each column whose value is greater than half the number of rows
-    let epsilon_rate = column_sums.iter().fold(0u32, |accum, count| {
-        (accum << 1) | ((*count > num_rows / 2) as u32)
-    });
-
-    // Since gamma is the opposite of epsilon, just use bitwise NOT on the epsilon number (but mask it
-    // so that we only "not" the bits in our input numbers)
-    let gamma_rate = !epsilon_rate & number_mask;
-    return epsilon_rate * gamma_rate;
+pub fn part1(report: &DiagnosticReport) -> Option<u64> {
+    let power_consumption =
+        to_number(&report.gamma_rate())?.checked_mul(to_number(&report.epsilon_rate())?)?;
+    Some(power_consumption)
 }
 
 #[aoc(day3, part2)]
-pub fn part2(binary_numbers: &[Vec<u8>]) -> u32 {
-    // Sum each line (and count the number of lines) into one big Vec<u32>
-    let (_, column_sums) =
-        binary_numbers
-            .iter()
-            .fold((0u32, vec![0u32; 0]), |(count, accum), row| {
-                // Length of the longest previous number in the sequence
-                let accumulated_number_bitlength = accum.len();
-                // Make sure that they are the same size -- left pad accum if it's not as long as this row
-                let accum = std::iter::repeat(0)
-                    .take(row.len().saturating_sub(accumulated_number_bitlength))
-                    .chain(accum);
-
-                // Make sure that they are the same size -- left pad row if it's not as long as the previous rows
-                let row = std::iter::repeat(&0u8)
-                    .take(accumulated_number_bitlength.saturating_sub(row.len()))
-                    .chain(row);
-
-                // Join the two iterators into tuples ala ((it1[0], it2[0]), (it1[1], it2[1]), ...) and then add
-                // them elementwise (it1[0] + it2[0], it1[1] + it2[1], ..).
-                let accum = accum
-                    .zip(row)
-                    .map(|(a, r)| a + (*r as u32))
-                    .collect::<Vec<_>>();
-
-                return (count + 1, accum);
-            });
-    let binary_number_length = column_sums.len();
-
-    let numbers = binary_numbers
-        .into_iter()
-        .map(|n| {
-            n.into_iter()
-                .fold(0u32, |accum, bit| (accum << 1) | (*bit as u32))
-        })
-        .collect::<Vec<_>>();
-
-    let mut oxygen_rating_candidates = numbers.clone();
-    for bit_index in 1..=binary_number_length {
-        let bit_num = binary_number_length - bit_index;
-        let num_set_bits = oxygen_rating_candidates
-            .iter()
-            .map(|n| (n >> bit_num) & 1)
-            .sum::<u32>();
-
-        let retain_bit = if num_set_bits >= oxygen_rating_candidates.len() as u32 - num_set_bits {
-            1u32
-        } else {
-            0u32
-        };
+pub fn part2(report: &DiagnosticReport) -> Option<u64> {
+    let oxygen_generator_rating =
+        to_number(report.filter_by_bit_criteria(&BitCriteria::OXYGEN_GENERATOR)?)?;
+    let co2_scrubber_rating =
+        to_number(report.filter_by_bit_criteria(&BitCriteria::CO2_SCRUBBER)?)?;
+    oxygen_generator_rating.checked_mul(co2_scrubber_rating)
+}
+
+#[aoc(day3, part2, Trie)]
+pub fn part2_trie(report: &DiagnosticReport) -> Option<u64> {
+    let trie = BitTrie::new(report);
+    let oxygen_generator_rating = to_number(&trie.rating(&BitCriteria::OXYGEN_GENERATOR)?)?;
+    let co2_scrubber_rating = to_number(&trie.rating(&BitCriteria::CO2_SCRUBBER)?)?;
+    oxygen_generator_rating.checked_mul(co2_scrubber_rating)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "00100\n\
+                         11110\n\
+                         10110\n\
+                         10111\n\
+                         10101\n\
+                         01111\n\
+                         00111\n\
+                         11100\n\
+                         10000\n\
+                         11001\n\
+                         00010\n\
+                         01010";
+
+    #[test]
+    fn test_part1() {
+        assert_eq!(part1(&generator(INPUT).unwrap()), Some(198));
+    }
+
+    #[test]
+    fn test_part2() {
+        assert_eq!(part2(&generator(INPUT).unwrap()), Some(230));
+        assert_eq!(part2_trie(&generator(INPUT).unwrap()), Some(230));
+    }
 
-        oxygen_rating_candidates.retain(|n| (n >> bit_num) & 1 == retain_bit);
-        if oxygen_rating_candidates.len() == 1 {
-            break;
+    #[test]
+    fn test_trie_matches_filter() {
+        let report = generator(INPUT).unwrap();
+        let trie = BitTrie::new(&report);
+        for commonality in [Commonality::MostCommon, Commonality::LeastCommon] {
+            for tie in [false, true] {
+                let criteria = BitCriteria { commonality, tie };
+                assert_eq!(
+                    trie.rating(&criteria).as_ref(),
+                    report.filter_by_bit_criteria(&criteria)
+                );
+            }
         }
+
+        // Duplicate rows share a path through the trie, and the filter keeps the first copy
+        let report = generator("10\n10\n01").unwrap();
+        let trie = BitTrie::new(&report);
+        for criteria in [BitCriteria::OXYGEN_GENERATOR, BitCriteria::CO2_SCRUBBER] {
+            assert_eq!(
+                trie.rating(&criteria).as_ref(),
+                report.filter_by_bit_criteria(&criteria)
+            );
+        }
+        assert_eq!(part2_trie(&report), part2(&report));
+
+        assert_eq!(
+            BitTrie::new(&generator("").unwrap()).rating(&BitCriteria::OXYGEN_GENERATOR),
+            None
+        );
     }
 
-    let mut co2_rating_candidates = numbers.clone();
-    for bit_index in 1..=binary_number_length {
-        let bit_num = binary_number_length - bit_index;
-        let num_set_bits = co2_rating_candidates
-            .iter()
-            .map(|n| (n >> bit_num) & 1)
-            .sum::<u32>();
+    #[test]
+    fn test_wide_rows() {
+        // 70 bits wide: the rates no longer fit in a u64, but the filters still work on the bits
+        let input = format!(
+            "{}01\n{}11\n{}10",
+            "1".repeat(68),
+            "0".repeat(68),
+            "0".repeat(68)
+        );
+        let report = generator(&input).unwrap();
+        assert_eq!(report.width(), 70);
+        assert_eq!(part1(&report), None);
 
-        let retain_bit = if num_set_bits < co2_rating_candidates.len() as u32 - num_set_bits {
-            1u32
-        } else {
-            0u32
+        let oxygen = report
+            .filter_by_bit_criteria(&BitCriteria::OXYGEN_GENERATOR)
+            .unwrap();
+        assert_eq!(oxygen, &report.rows()[1]);
+        let co2 = report
+            .filter_by_bit_criteria(&BitCriteria::CO2_SCRUBBER)
+            .unwrap();
+        assert_eq!(co2, &report.rows()[0]);
+    }
+
+    #[test]
+    fn test_tie_breaking() {
+        let report = generator("10\n01").unwrap();
+        let prefer = |commonality, tie| {
+            report
+                .filter_by_bit_criteria(&BitCriteria { commonality, tie })
+                .and_then(to_number)
         };
+        assert_eq!(prefer(Commonality::MostCommon, true), Some(0b10));
+        assert_eq!(prefer(Commonality::MostCommon, false), Some(0b01));
+        assert_eq!(prefer(Commonality::LeastCommon, true), Some(0b10));
+        assert_eq!(prefer(Commonality::LeastCommon, false), Some(0b01));
+    }
 
-        co2_rating_candidates.retain(|n| (n >> bit_num) & 1 == retain_bit);
-        if co2_rating_candidates.len() == 1 {
-            break;
-        }
+    #[test]
+    fn test_duplicate_rows() {
+        let report = generator("10\n10\n01").unwrap();
+        assert_eq!(
+            report.filter_by_bit_criteria(&BitCriteria::OXYGEN_GENERATOR),
+            Some(&report.rows()[0])
+        );
+        assert_eq!(part2(&report), Some(2));
     }
 
-    // Life support rating
-    return oxygen_rating_candidates[0] * co2_rating_candidates[0];
+    #[test]
+    fn test_invalid_digit() {
+        assert!(generator("0101\n0121").is_err());
+    }
 }
@@ -1,7 +1,11 @@
-use aoc_runner_derive::{aoc, aoc_generator};
+use std::collections::HashMap;
 
+use aoc_2021::{AocMap, Point};
+use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
 
+use anyhow::{anyhow, bail, Result};
+
 #[derive(Copy, Clone, Debug)]
 pub enum State {
     Uncalled(i32),
@@ -10,168 +14,368 @@ pub enum State {
 
 impl State {
     fn is_called(&self) -> bool {
-        match self {
-            State::Called(_) => true,
-            _ => false,
-        }
+        matches!(self, State::Called(_))
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+// The puzzle itself only uses the standard rows and columns, the rest are for playing variants
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WinPattern {
+    // Any complete row
+    Row,
+    // Any complete column
+    Column,
+    // Either complete diagonal, only on square boards
+    Diagonal,
+    // All four corners
+    Corners,
+    // Every cell on the board
+    Blackout,
+}
+
+impl WinPattern {
+    pub const STANDARD: [Self; 2] = [Self::Row, Self::Column];
+}
+
+#[derive(Clone, Debug)]
 pub struct Board {
-    numbers: [[State; 5]; 5],
+    numbers: AocMap<State>,
+    // Number of called cells in each row and column, and in total, so the common win checks don't need to
+    // look at the cells
+    called_per_row: Vec<usize>,
+    called_per_column: Vec<usize>,
+    num_called: usize,
 }
 
 impl Board {
-    fn has_won(&self, called_row: usize, called_column: usize) -> bool {
-        let row_complete = self.numbers[called_row].iter().all(State::is_called);
-        if row_complete {
-            return true;
+    fn parse(rows: &[&str]) -> Result<Self> {
+        let mut values = Vec::new();
+        let mut width = None;
+        for row in rows {
+            let numbers = row
+                .split_whitespace()
+                .map(|n| {
+                    n.parse::<i32>()
+                        .map(State::Uncalled)
+                        .map_err(|_| anyhow!("Invalid number '{}' in board row: {}", n, row))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            match width {
+                Some(width) if width != numbers.len() => bail!(
+                    "Board row has {} numbers, expected {}: {}",
+                    numbers.len(),
+                    width,
+                    row
+                ),
+                _ => width = Some(numbers.len()),
+            }
+            values.extend(numbers);
         }
 
-        let column_complete = self
-            .numbers
-            .iter()
-            .map(|row| row.get(called_column).unwrap())
-            .all(State::is_called);
-        return column_complete;
+        let width = width.ok_or_else(|| anyhow!("Empty board"))?;
+        Ok(Self {
+            numbers: AocMap {
+                size: Point::new(width, rows.len()),
+                values,
+            },
+            called_per_row: vec![0; rows.len()],
+            called_per_column: vec![0; width],
+            num_called: 0,
+        })
     }
 
-    fn get_score(&self, winning_row: usize, winning_column: usize) -> i32 {
+    // Mark the cell as called, returning false if it already was
+    fn mark(&mut self, cell: Point) -> bool {
+        match self.numbers.get(cell) {
+            State::Uncalled(number) => {
+                self.numbers.set(cell, State::Called(number));
+                self.called_per_row[cell.y] += 1;
+                self.called_per_column[cell.x] += 1;
+                self.num_called += 1;
+                true
+            }
+            State::Called(_) => false,
+        }
+    }
+
+    fn all_called<I>(&self, mut points: I) -> bool
+    where
+        I: Iterator<Item = Point>,
+    {
+        points.all(|p| self.numbers.get(p).is_called())
+    }
+
+    // Whether the board has won through any of the patterns, only looking at the patterns that involve the
+    // most recently called point
+    fn has_won(&self, called: Point, patterns: &[WinPattern]) -> bool {
+        let size = self.numbers.size;
+        let last = Point::new(size.x - 1, size.y - 1);
+        patterns.iter().any(|pattern| match pattern {
+            WinPattern::Row => self.called_per_row[called.y] == size.x,
+            WinPattern::Column => self.called_per_column[called.x] == size.y,
+            WinPattern::Diagonal if size.x == size.y => {
+                (called.x == called.y && self.all_called((0..size.x).map(|i| Point::new(i, i))))
+                    || (called.x == last.x - called.y
+                        && self.all_called((0..size.x).map(|i| Point::new(i, last.x - i))))
+            }
+            WinPattern::Diagonal => false,
+            WinPattern::Corners => {
+                let corners = [
+                    Point::new(0, 0),
+                    Point::new(last.x, 0),
+                    Point::new(0, last.y),
+                    last,
+                ];
+                corners.contains(&called) && self.all_called(corners.into_iter())
+            }
+            WinPattern::Blackout => self.num_called == self.numbers.values.len(),
+        })
+    }
+
+    fn get_score(&self, winning_cell: Point) -> i32 {
         let uncalled_sum = self
             .numbers
+            .values
             .iter()
-            .map(|row| {
-                row.iter()
-                    .filter_map(|v| match v {
-                        State::Uncalled(n) => Some(n),
-                        _ => None,
-                    })
-                    .sum::<i32>()
+            .filter_map(|v| match v {
+                State::Uncalled(n) => Some(n),
+                _ => None,
             })
             .sum::<i32>();
-        let winning_cell = &self.numbers[winning_row][winning_column];
-        match winning_cell {
+        match self.numbers.get(winning_cell) {
             State::Called(number) => uncalled_sum * number,
-            _ => panic!("What"),
+            _ => panic!("Winning cell {:?} was never called", winning_cell),
         }
     }
 }
-#[aoc_generator(day4)]
-pub fn generator(input: &str) -> (Vec<i32>, Vec<Board>) {
-    let mut line_reader = input.lines();
 
-    let called_numbers = line_reader.next().unwrap();
-    let called_numbers = called_numbers
-        .trim_end()
-        .split(",")
-        .map(|n| n.parse::<i32>().unwrap());
-
-    let board_reader = line_reader.chunks(6);
-    let boards = board_reader
-        .into_iter()
-        .map(|board_lines| {
-            let board_lines = board_lines.skip(1);
-            let mut board_rows = board_lines.map(|line| {
-                let mut numbers = line
-                    .split_whitespace()
-                    .map(str::parse::<i32>)
-                    .map(Result::unwrap)
-                    .map(State::Uncalled);
-                [
-                    numbers.next().unwrap(),
-                    numbers.next().unwrap(),
-                    numbers.next().unwrap(),
-                    numbers.next().unwrap(),
-                    numbers.next().unwrap(),
-                ]
-            });
-
-            let numbers = [
-                board_rows.next(),
-                board_rows.next(),
-                board_rows.next(),
-                board_rows.next(),
-                board_rows.next(),
-            ];
-            if numbers.last().is_none() {
-                None
-            } else {
-                Some(Board {
-                    numbers: numbers.map(Option::unwrap),
-                })
-            }
-        })
-        .filter_map(|x| x);
+#[derive(Clone, Debug)]
+pub struct Bingo {
+    called_numbers: Vec<i32>,
+    boards: Vec<Board>,
+}
 
-    (called_numbers.collect(), boards.collect())
+#[derive(Clone, Debug, PartialEq)]
+pub struct Win {
+    pub board: usize,
+    // Index into the called numbers of the number that made the board win
+    pub turn: usize,
+    pub score: i32,
 }
 
-#[aoc(day4, part1)]
-pub fn part1((called_numbers, boards): &(Vec<i32>, Vec<Board>)) -> Option<i32> {
-    let mut boards = boards.clone();
-    for called_number in called_numbers {
-        let called_number = *called_number;
-        for board_index in 0..boards.len() {
-            let board = &mut boards[board_index];
-            'row_loop: for row in 0..board.numbers.len() {
-                for column in 0..board.numbers[row].len() {
-                    match board.numbers[row][column] {
-                        State::Uncalled(number) if number == called_number => {
-                            board.numbers[row][column] = State::Called(number);
-                            if board.has_won(row, column) {
-                                return Some(board.get_score(row, column));
-                            }
-                            break 'row_loop;
-                        }
-                        _ => {}
-                    }
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Ranking {
+    // In the order that the boards won, ordered by board index for boards winning on the same turn
+    pub wins: Vec<Win>,
+    pub never_won: Vec<usize>,
+}
+
+impl Bingo {
+    // Where each number appears, as (board index, cell) pairs
+    fn index(&self) -> HashMap<i32, Vec<(usize, Point)>> {
+        let mut index: HashMap<i32, Vec<(usize, Point)>> = HashMap::new();
+        for (board_index, board) in self.boards.iter().enumerate() {
+            for cell in board.numbers.coordinates() {
+                if let State::Uncalled(number) = board.numbers.get(cell) {
+                    index.entry(number).or_default().push((board_index, cell));
                 }
             }
         }
+        index
     }
 
-    None
+    // Play the game to completion, only touching the cells that each called number appears in. A number can
+    // appear more than once on a board, so every copy of it gets marked before checking for a win.
+    pub fn play(&self, patterns: &[WinPattern]) -> Ranking {
+        let index = self.index();
+        let mut boards = self.boards.clone();
+        let mut has_won = vec![false; boards.len()];
+        let mut ranking = Ranking::default();
+        for (turn, called_number) in self.called_numbers.iter().enumerate() {
+            let cells = index.get(called_number).into_iter().flatten();
+            for (board_index, cells) in &cells.group_by(|(board_index, _)| *board_index) {
+                if has_won[board_index] {
+                    continue;
+                }
+                let board = &mut boards[board_index];
+                let marked = cells
+                    .filter(|(_, cell)| board.mark(*cell))
+                    .map(|(_, cell)| *cell)
+                    .collect::<Vec<_>>();
+                if marked.iter().any(|cell| board.has_won(*cell, patterns)) {
+                    has_won[board_index] = true;
+                    ranking.wins.push(Win {
+                        board: board_index,
+                        turn,
+                        score: board.get_score(marked[0]),
+                    });
+                }
+            }
+        }
+
+        ranking.never_won = has_won.iter().positions(|has_won| !has_won).collect();
+        ranking
+    }
+}
+
+// Boards are separated by one or more blank lines, and can be of any size
+#[aoc_generator(day4)]
+pub fn generator(input: &str) -> Result<Bingo> {
+    let mut line_reader = input.lines().map(str::trim);
+
+    let called_numbers = line_reader
+        .find(|line| !line.is_empty())
+        .ok_or_else(|| anyhow!("Missing called numbers"))?;
+    let called_numbers = called_numbers
+        .split(',')
+        .map(|n| {
+            n.trim()
+                .parse::<i32>()
+                .map_err(|_| anyhow!("Invalid called number '{}'", n))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let lines = line_reader.collect::<Vec<_>>();
+    let boards = lines
+        .split(|line| line.is_empty())
+        .filter(|board_lines| !board_lines.is_empty())
+        .map(Board::parse)
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Bingo {
+        called_numbers,
+        boards,
+    })
+}
+
+#[aoc(day4, part1)]
+pub fn part1(bingo: &Bingo) -> Option<i32> {
+    bingo
+        .play(&WinPattern::STANDARD)
+        .wins
+        .first()
+        .map(|win| win.score)
 }
 
 #[aoc(day4, part2)]
-pub fn part2((called_numbers, boards): &(Vec<i32>, Vec<Board>)) -> Option<i32> {
-    let mut boards = boards.clone();
-    let mut last_win = None;
-    for called_number in called_numbers {
-        let called_number = *called_number;
-        let mut finished_boards = Vec::new();
-        let mut last_board_score = None;
-        for board_index in 0..boards.len() {
-            let board = &mut boards[board_index];
-            'row_loop: for row in 0..board.numbers.len() {
-                for column in 0..board.numbers[row].len() {
-                    match board.numbers[row][column] {
-                        State::Uncalled(number) if number == called_number => {
-                            board.numbers[row][column] = State::Called(number);
-                            if board.has_won(row, column) {
-                                let score = board.get_score(row, column);
-                                finished_boards.push(board_index);
-                                last_board_score = Some(score);
-                            }
-                            break 'row_loop;
-                        }
-                        _ => {}
-                    }
-                }
+pub fn part2(bingo: &Bingo) -> Option<i32> {
+    bingo
+        .play(&WinPattern::STANDARD)
+        .wins
+        .last()
+        .map(|win| win.score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "7,4,9,5,11,17,23,2,0,14,21,24,10,16,13,6,15,25,12,22,18,20,8,19,3,26,1\n\
+                         \n\
+                         22 13 17 11  0\n\
+                         8  2 23  4 24\n\
+                         21  9 14 16  7\n\
+                         6 10  3 18  5\n\
+                         1 12 20 15 19\n\
+                         \n\
+                         3 15  0  2 22\n\
+                         9 18 13 17  5\n\
+                         19  8  7 25 23\n\
+                         20 11 10 24  4\n\
+                         14 21 16 12  6\n\
+                         \n\
+                         14 21 17 24  4\n\
+                         10 16 15  9 19\n\
+                         18  8 23 26 20\n\
+                         22 11 13  6  5\n\
+                         2  0 12  3  7";
+
+    #[test]
+    fn test_part1() {
+        assert_eq!(part1(&generator(INPUT).unwrap()), Some(4512));
+    }
+
+    #[test]
+    fn test_part2() {
+        assert_eq!(part2(&generator(INPUT).unwrap()), Some(1924));
+    }
+
+    #[test]
+    fn test_ranking() {
+        let mut input = INPUT.to_owned();
+        // A fourth board that never wins
+        input.push_str("\n\n90 91\n92 93");
+        assert_eq!(
+            generator(&input).unwrap().play(&WinPattern::STANDARD),
+            Ranking {
+                wins: vec![
+                    Win {
+                        board: 2,
+                        turn: 11,
+                        score: 4512
+                    },
+                    Win {
+                        board: 0,
+                        turn: 13,
+                        score: 2192
+                    },
+                    Win {
+                        board: 1,
+                        turn: 14,
+                        score: 1924
+                    },
+                ],
+                never_won: vec![3],
             }
-        }
+        );
+    }
 
-        finished_boards.reverse();
-        for board_index in finished_boards {
-            boards.swap_remove(board_index);
-        }
+    #[test]
+    fn test_patterns() {
+        let bingo = generator(
+            "1,5,9,3,7\n\n\n\
+             1 2 3\n\
+             4 5 6\n\
+             7 8 9\n   \n\
+             9 4 3 8\n\
+             2 5 6 7",
+        )
+        .unwrap();
+        let scores = |patterns: &[WinPattern]| {
+            bingo
+                .play(patterns)
+                .wins
+                .into_iter()
+                .map(|win| win.score)
+                .collect_vec()
+        };
+        // Only the square board has diagonals, and it completes one on the third call
+        assert_eq!(scores(&[WinPattern::Diagonal]), vec![(45 - 15) * 9]);
+        // The 3x3 board gets its corners on the fifth call, the 2x4 board never does
+        assert_eq!(scores(&[WinPattern::Corners]), vec![(45 - 25) * 7]);
+        assert_eq!(scores(&[WinPattern::Blackout]), vec![]);
+        assert_eq!(scores(&WinPattern::STANDARD), vec![]);
+    }
 
-        if boards.len() == 0 {
-            last_win = last_board_score;
-            break;
-        }
+    #[test]
+    fn test_duplicate_numbers() {
+        // Calling 1 completes the top row, and the second 1 has to be marked before scoring
+        let bingo = generator("2,1\n\n1 2\n3 1").unwrap();
+        assert_eq!(
+            bingo.play(&WinPattern::STANDARD).wins,
+            vec![Win {
+                board: 0,
+                turn: 1,
+                score: 3
+            }]
+        );
     }
 
-    last_win
+    #[test]
+    fn test_malformed_boards() {
+        assert!(generator("1,2\n\n1 2\n3").is_err());
+        assert!(generator("1,2\n\n1 x\n3 4").is_err());
+        assert!(generator("1,a\n\n1 2\n3 4").is_err());
+        assert!(generator("").is_err());
+    }
 }
@@ -1,9 +1,12 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 
+use aoc_2021::{AocMap, Point as MapPoint};
 use itertools::Itertools;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Debug, Display};
 use std::iter::repeat;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Point {
     x: u32,
     y: u32,
@@ -33,6 +36,23 @@ impl Slope {
     }
 }
 
+// How to turn a line at an arbitrary angle into grid points
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rasterization {
+    // Only the lattice points that lie exactly on the line
+    Exact,
+    // The closest grid point in every column or row, whichever the line spans more of
+    Bresenham,
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
 #[derive(Debug)]
 pub enum Line {
     Horizontal {
@@ -51,6 +71,11 @@ pub enum Line {
         y_start: u32,
         slope: Slope,
     },
+    // Any other angle
+    General {
+        start: Point,
+        end: Point,
+    },
 }
 
 impl Line {
@@ -89,37 +114,212 @@ impl Line {
                 },
             }
         } else {
-            panic!("Unsupported line type");
+            Self::General { start: p1, end: p2 }
+        }
+    }
+
+    // The two ends of the line, not necessarily in the order they were given
+    pub fn endpoints(&self) -> (Point, Point) {
+        match self {
+            Self::Horizontal { x_min, x_max, y } => {
+                (Point::new((*x_min, *y)), Point::new((*x_max, *y)))
+            }
+            Self::Vertical { x, y_min, y_max } => {
+                (Point::new((*x, *y_min)), Point::new((*x, *y_max)))
+            }
+            Self::Diagonal {
+                x_min,
+                x_max,
+                y_start,
+                slope,
+            } => (
+                Point::new((*x_min, *y_start)),
+                Point::new((*x_max, slope.apply(*y_start, x_max - x_min))),
+            ),
+            Self::General { start, end } => (*start, *end),
+        }
+    }
+
+    // Every grid point covered by the line. Horizontal, vertical and diagonal lines cover the same points
+    // with either rasterization, so they skip the general stepping.
+    pub fn points(&self, rasterization: Rasterization) -> Vec<(u32, u32)> {
+        match self {
+            Self::Horizontal { x_min, x_max, y } => (*x_min..=*x_max).zip(repeat(*y)).collect(),
+            Self::Vertical { x, y_min, y_max } => repeat(*x).zip(*y_min..=*y_max).collect(),
+            Self::Diagonal {
+                x_min,
+                x_max,
+                y_start,
+                slope,
+            } => (*x_min..=*x_max)
+                .enumerate()
+                .map(|(step, x)| (x, slope.apply(*y_start, step as u32)))
+                .collect(),
+            Self::General { start, end } => match rasterization {
+                Rasterization::Exact => Self::exact_points(*start, *end),
+                Rasterization::Bresenham => Self::bresenham_points(*start, *end),
+            },
+        }
+    }
+
+    // Dividing the line by the gcd of its extents gives the smallest step that lands on lattice points
+    fn exact_points(start: Point, end: Point) -> Vec<(u32, u32)> {
+        let dx = end.x as i64 - start.x as i64;
+        let dy = end.y as i64 - start.y as i64;
+        let num_steps = gcd(dx.unsigned_abs() as u32, dy.unsigned_abs() as u32) as i64;
+        let (step_x, step_y) = (dx / num_steps, dy / num_steps);
+        (0..=num_steps)
+            .map(|i| {
+                (
+                    (start.x as i64 + i * step_x) as u32,
+                    (start.y as i64 + i * step_y) as u32,
+                )
+            })
+            .collect()
+    }
+
+    fn bresenham_points(start: Point, end: Point) -> Vec<(u32, u32)> {
+        let (mut x, mut y) = (start.x as i64, start.y as i64);
+        let dx = (end.x as i64 - x).abs();
+        let dy = -(end.y as i64 - y).abs();
+        let step_x = if start.x < end.x { 1 } else { -1 };
+        let step_y = if start.y < end.y { 1 } else { -1 };
+        let mut error = dx + dy;
+
+        let mut points = Vec::with_capacity(dx.max(-dy) as usize + 1);
+        loop {
+            points.push((x as u32, y as u32));
+            if x == end.x as i64 && y == end.y as i64 {
+                return points;
+            }
+            let doubled_error = 2 * error;
+            if doubled_error >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if doubled_error <= dx {
+                error += dx;
+                y += step_y;
+            }
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-enum Overlap {
-    None,
-    One,
-    Multiple,
+// How many lines cover a point, shown the same way as the puzzle's diagrams
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct Coverage(u32);
+
+impl Debug for Coverage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            0 => write!(f, "."),
+            count @ 1..=9 => write!(f, "{}", count),
+            _ => write!(f, "#"),
+        }
+    }
+}
+
+// Exact number of lines covering every point of a dense grid
+pub struct HeatMap {
+    pub counts: AocMap<Coverage>,
 }
 
-impl Overlap {
-    fn increment(&mut self) {
-        *self = match self {
-            Overlap::None => Overlap::One,
-            Overlap::One => Overlap::Multiple,
-            Overlap::Multiple => Overlap::Multiple,
+impl HeatMap {
+    pub fn new<F>(
+        (width, height, lines): &(u64, u64, Vec<Line>),
+        include: F,
+        rasterization: Rasterization,
+    ) -> Self
+    where
+        F: Fn(&Line) -> bool,
+    {
+        let size = MapPoint::new(*width as usize, *height as usize);
+        let mut counts = AocMap {
+            size,
+            values: vec![Coverage::default(); size.x * size.y],
+        };
+        for line in lines.iter().filter(|line| include(line)) {
+            for (x, y) in line.points(rasterization) {
+                counts.values[y as usize * size.x + x as usize].0 += 1;
+            }
         }
+        Self { counts }
+    }
+
+    pub fn count_overlaps(&self) -> usize {
+        self.counts
+            .values
+            .iter()
+            .filter(|count| count.0 >= 2)
+            .count()
     }
 }
 
+// For looking at surveys rather than solving the puzzle
+impl HeatMap {
+    // Number of points at each overlap level, including the points that no line covers
+    pub fn histogram(&self) -> BTreeMap<u32, usize> {
+        self.counts
+            .values
+            .iter()
+            .map(|count| count.0)
+            .counts()
+            .into_iter()
+            .collect()
+    }
+
+    // The highest overlap level and every point that reaches it, in reading order
+    pub fn hottest_points(&self) -> (u32, Vec<MapPoint>) {
+        let max = self
+            .counts
+            .values
+            .iter()
+            .map(|count| count.0)
+            .max()
+            .unwrap_or(0);
+        let points = self
+            .counts
+            .coordinates()
+            .filter(|p| self.counts.get(*p).0 == max)
+            .collect();
+        (max, points)
+    }
+
+    // Plain PGM greyscale image, where the brightest points have the most overlaps
+    pub fn to_pgm(&self) -> String {
+        let (max, _) = self.hottest_points();
+        let mut image = format!(
+            "P2\n{} {}\n{}\n",
+            self.counts.size.x,
+            self.counts.size.y,
+            max.max(1)
+        );
+        for row in self.counts.values.chunks(self.counts.size.x) {
+            image.push_str(&row.iter().map(|count| count.0).join(" "));
+            image.push('\n');
+        }
+        image
+    }
+}
+
+// The same diagram as the puzzle uses
+impl Display for HeatMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.counts)
+    }
+}
+
+// Along with the lines, the size of a grid that fits them all. That's one more than the largest coordinate,
+// which can itself be `u32::MAX`.
 #[aoc_generator(day5)]
-pub fn generator(input: &str) -> (u32, u32, Vec<Line>) {
+pub fn generator(input: &str) -> (u64, u64, Vec<Line>) {
     let mut max_x = 0u32;
     let mut max_y = 0u32;
     let lines = input
         .lines()
-        .map(|line| {
+        .filter_map(|line| {
             let components = line.split(" -> ").map(|comp| {
-                comp.split(",")
+                comp.split(',')
                     .map(str::parse::<u32>)
                     .filter_map(|v| v.ok())
             });
@@ -134,87 +334,460 @@ pub fn generator(input: &str) -> (u32, u32, Vec<Line>) {
                 None
             }
         })
-        .filter_map(|line| line)
         .collect();
 
-    (max_x + 1, max_y + 1, lines)
+    (max_x as u64 + 1, max_y as u64 + 1, lines)
 }
 
-#[aoc(day5, part1)]
-pub fn part1((width, height, lines): &(u32, u32, Vec<Line>)) -> u16 {
-    let (width, height) = (*width, *height);
+// The exact lattice points of a line as `start + i * step` for `i` in `0..=num_steps`, where `step` is the
+// smallest step that stays on lattice points. A step can be nearly as long as the coordinates themselves,
+// so the cross and dot products of two steps need more than 64 bits.
+#[derive(Clone, Copy, Debug)]
+struct Segment {
+    start: (i128, i128),
+    step: (i128, i128),
+    num_steps: i128,
+    x_range: (i128, i128),
+    y_range: (i128, i128),
+}
+
+fn cross(a: (i128, i128), b: (i128, i128)) -> i128 {
+    a.0 * b.1 - a.1 * b.0
+}
+
+fn dot(a: (i128, i128), b: (i128, i128)) -> i128 {
+    a.0 * b.0 + a.1 * b.1
+}
+
+// The whole infinite line that a segment lies on, the same for every segment on it no matter which way
+// they go
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct Support {
+    // Pointing right, or down for vertical lines
+    step: (i128, i128),
+    // Which of the parallel lines with this step it is
+    offset: i128,
+}
+
+impl Support {
+    // Consecutive lattice points on the line have consecutive indices
+    fn index(&self, point: (i128, i128)) -> i128 {
+        dot(point, self.step).div_euclid(dot(self.step, self.step))
+    }
+}
 
-    let mut points = Vec::new();
-    points.resize((height * width) as usize, Overlap::None);
+// Everything that two or more segments share, kept as runs along lines rather than point by point, since
+// collinear segments can share billions of points
+#[derive(Debug, Default)]
+struct SharedPoints {
+    // Index ranges along each line, possibly overlapping each other
+    runs: HashMap<Support, Vec<(i128, i128)>>,
+    crossings: Vec<Crossing>,
+}
 
-    let mut num_points_with_multiple_overlaps = 0u16;
-    for line in lines {
-        let mark_point = |(x, y)| {
-            let coord = (y * width + x) as usize;
-            if let Overlap::One = points[coord] {
-                num_points_with_multiple_overlaps += 1
+// A point where two segments meet, along with the lines of those segments, to tell whether one of the runs
+// already covers it
+#[derive(Debug)]
+struct Crossing {
+    point: (i128, i128),
+    supports: [Option<Support>; 2],
+}
+
+impl SharedPoints {
+    fn count(mut self) -> usize {
+        let mut total = 0;
+        for runs in self.runs.values_mut() {
+            runs.sort_unstable();
+            let mut merged: Vec<(i128, i128)> = Vec::with_capacity(runs.len());
+            for &(first, last) in runs.iter() {
+                match merged.last_mut() {
+                    Some(previous) if first <= previous.1 + 1 => previous.1 = previous.1.max(last),
+                    _ => merged.push((first, last)),
+                }
             }
-            points[coord].increment();
-        };
+            total += merged
+                .iter()
+                .map(|(first, last)| last - first + 1)
+                .sum::<i128>();
+            *runs = merged;
+        }
 
-        match line {
-            Line::Horizontal { x_min, x_max, y } => {
-                (*x_min..=*x_max).zip(repeat(*y)).for_each(mark_point)
+        let in_run = |point: (i128, i128), support: &Support| {
+            let runs = match self.runs.get(support) {
+                Some(runs) => runs,
+                None => return false,
+            };
+            let index = support.index(point);
+            let after = runs.partition_point(|(first, _)| *first <= index);
+            after > 0 && runs[after - 1].1 >= index
+        };
+        // A crossing that's already part of a run is already counted, and one where runs on several lines
+        // cross was counted once for each of them
+        let mut covering_runs = HashMap::<_, Vec<Support>>::new();
+        for Crossing { point, supports } in &self.crossings {
+            let covering = covering_runs.entry(*point).or_default();
+            for support in supports.iter().flatten() {
+                if in_run(*point, support) && !covering.contains(support) {
+                    covering.push(*support);
+                }
             }
-            Line::Vertical { x, y_min, y_max } => {
-                repeat(*x).zip(*y_min..=*y_max).for_each(mark_point)
+        }
+        for covering in covering_runs.values() {
+            match covering.len() {
+                0 => total += 1,
+                num_runs => total -= num_runs as i128 - 1,
             }
-            Line::Diagonal {
-                x_min: _,
-                x_max: _,
-                y_start: _,
-                slope: _,
-            } => {}
-        };
-    }
+        }
 
-    num_points_with_multiple_overlaps
+        total as usize
+    }
 }
 
-#[aoc(day5, part2)]
-pub fn part2((width, height, lines): &(u32, u32, Vec<Line>)) -> u16 {
-    let (width, height) = (*width, *height);
+impl Segment {
+    fn new(line: &Line) -> Self {
+        let (start, end) = line.endpoints();
+        let start = (start.x as i128, start.y as i128);
+        let end = (end.x as i128, end.y as i128);
+        let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+        // A single point has no direction, so any step will do as long as we never take it
+        let num_steps = (gcd(dx.unsigned_abs() as u32, dy.unsigned_abs() as u32) as i128).max(1);
+        Self {
+            start,
+            step: (dx / num_steps, dy / num_steps),
+            num_steps: if dx == 0 && dy == 0 { 0 } else { num_steps },
+            x_range: (start.0.min(end.0), start.0.max(end.0)),
+            y_range: (start.1.min(end.1), start.1.max(end.1)),
+        }
+    }
 
-    let mut points = Vec::new();
-    points.resize((height * width) as usize, Overlap::None);
+    fn point(&self, i: i128) -> (i128, i128) {
+        (
+            self.start.0 + i * self.step.0,
+            self.start.1 + i * self.step.1,
+        )
+    }
 
-    let mut num_points_with_multiple_overlaps = 0u16;
-    for line in lines {
-        let mark_point = |(x, y)| {
-            let coord = (y * width + x) as usize;
-            if let Overlap::One = points[coord] {
-                num_points_with_multiple_overlaps += 1
-            }
-            points[coord].increment();
+    // Single points don't lie on any particular line
+    fn support(&self) -> Option<Support> {
+        if self.num_steps == 0 {
+            return None;
+        }
+        let step = if self.step.0 < 0 || (self.step.0 == 0 && self.step.1 < 0) {
+            (-self.step.0, -self.step.1)
+        } else {
+            self.step
         };
+        Some(Support {
+            step,
+            offset: cross(self.start, step),
+        })
+    }
+
+    fn contains(&self, point: (i128, i128)) -> bool {
+        let offset = (point.0 - self.start.0, point.1 - self.start.1);
+        if self.num_steps == 0 || cross(offset, self.step) != 0 {
+            return offset == (0, 0);
+        }
+        let step_length = dot(self.step, self.step);
+        let along = dot(offset, self.step);
+        along % step_length == 0 && (0..=self.num_steps).contains(&(along / step_length))
+    }
+
+    // Add every lattice point that both segments cover
+    fn add_shared_points(&self, other: &Self, shared: &mut SharedPoints) {
+        let supports = [self.support(), other.support()];
 
-        match line {
-            Line::Horizontal { x_min, x_max, y } => {
-                (*x_min..=*x_max).zip(repeat(*y)).for_each(mark_point)
+        // Single points don't have a direction to intersect with
+        if other.num_steps == 0 || self.num_steps == 0 {
+            let (point, line) = if other.num_steps == 0 {
+                (other, self)
+            } else {
+                (self, other)
+            };
+            if line.contains(point.start) {
+                shared.crossings.push(Crossing {
+                    point: point.start,
+                    supports,
+                });
             }
-            Line::Vertical { x, y_min, y_max } => {
-                repeat(*x).zip(*y_min..=*y_max).for_each(mark_point)
+            return;
+        }
+
+        let offset = (other.start.0 - self.start.0, other.start.1 - self.start.1);
+        let denominator = cross(self.step, other.step);
+        if denominator != 0 {
+            // The lines cross at a single point, which only counts if it's a lattice point of both
+            let i = cross(offset, other.step);
+            let j = cross(offset, self.step);
+            if i % denominator == 0 && j % denominator == 0 {
+                let (i, j) = (i / denominator, j / denominator);
+                if (0..=self.num_steps).contains(&i) && (0..=other.num_steps).contains(&j) {
+                    shared.crossings.push(Crossing {
+                        point: self.point(i),
+                        supports,
+                    });
+                }
             }
-            Line::Diagonal {
-                x_min,
-                x_max,
-                y_start,
-                slope,
-            } => {
-                let points = (*x_min..=*x_max)
-                    .enumerate()
-                    .map(|(step, x)| (x, slope.apply(*y_start, step as u32)))
-                    .collect::<Vec<_>>();
+            return;
+        }
+
+        // Parallel lines only share points when they're on the same line, in which case both use the same
+        // step up to direction, and `other` covers a range of our step indices
+        if cross(offset, self.step) != 0 {
+            return;
+        }
+        let other_start = dot(offset, self.step) / dot(self.step, self.step);
+        let same_direction = self.step == other.step;
+        let other_end = other_start
+            + if same_direction {
+                other.num_steps
+            } else {
+                -other.num_steps
+            };
+        let first = other_start.min(other_end).max(0);
+        let last = other_start.max(other_end).min(self.num_steps);
+        if first <= last {
+            let support = supports[0].unwrap();
+            let (first, last) = (
+                support.index(self.point(first)),
+                support.index(self.point(last)),
+            );
+            shared
+                .runs
+                .entry(support)
+                .or_default()
+                .push((first.min(last), first.max(last)));
+        }
+    }
+}
 
-                points.into_iter().for_each(mark_point);
+// Unlike the heat map, which is kept as a reference, this sweeps across the lines from left to right, only
+// intersecting pairs whose bounding boxes overlap. Memory only depends on the number of lines and the
+// number of places where they meet, not on how far apart the coordinates are or how long the collinear
+// overlaps are. Lines are always rasterized exactly.
+fn count_overlaps_by_intersection<F>(lines: &[Line], include: F) -> usize
+where
+    F: Fn(&Line) -> bool,
+{
+    let segments = lines
+        .iter()
+        .filter(|line| include(line))
+        .map(Segment::new)
+        .sorted_by_key(|segment| segment.x_range.0)
+        .collect::<Vec<_>>();
+
+    let mut active: Vec<Segment> = Vec::new();
+    let mut shared = SharedPoints::default();
+    for segment in segments {
+        active.retain(|other| other.x_range.1 >= segment.x_range.0);
+        for other in &active {
+            if other.y_range.0 <= segment.y_range.1 && segment.y_range.0 <= other.y_range.1 {
+                segment.add_shared_points(other, &mut shared);
             }
-        };
+        }
+        active.push(segment);
+    }
+
+    shared.count()
+}
+
+fn is_straight(line: &Line) -> bool {
+    matches!(line, Line::Horizontal { .. } | Line::Vertical { .. })
+}
+
+#[aoc(day5, part1)]
+pub fn part1(input: &(u64, u64, Vec<Line>)) -> usize {
+    HeatMap::new(input, is_straight, Rasterization::Exact).count_overlaps()
+}
+
+#[aoc(day5, part1, Sweep)]
+pub fn part1_sweep((_, _, lines): &(u64, u64, Vec<Line>)) -> usize {
+    count_overlaps_by_intersection(lines, is_straight)
+}
+
+// The puzzle input only has the three fast path kinds of lines, so the rasterization only matters for
+// other surveys
+#[aoc(day5, part2)]
+pub fn part2(input: &(u64, u64, Vec<Line>)) -> usize {
+    HeatMap::new(input, |_| true, Rasterization::Exact).count_overlaps()
+}
+
+#[aoc(day5, part2, Bresenham)]
+pub fn part2_bresenham(input: &(u64, u64, Vec<Line>)) -> usize {
+    HeatMap::new(input, |_| true, Rasterization::Bresenham).count_overlaps()
+}
+
+#[aoc(day5, part2, Sweep)]
+pub fn part2_sweep((_, _, lines): &(u64, u64, Vec<Line>)) -> usize {
+    count_overlaps_by_intersection(lines, |_| true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "0,9 -> 5,9\n\
+                         8,0 -> 0,8\n\
+                         9,4 -> 3,4\n\
+                         2,2 -> 2,1\n\
+                         7,0 -> 7,4\n\
+                         6,4 -> 2,0\n\
+                         0,9 -> 2,9\n\
+                         3,4 -> 1,4\n\
+                         0,0 -> 8,8\n\
+                         5,5 -> 8,2";
+
+    #[test]
+    fn test_part1() {
+        assert_eq!(part1(&generator(INPUT)), 5);
+        assert_eq!(part1_sweep(&generator(INPUT)), 5);
     }
 
-    num_points_with_multiple_overlaps
+    #[test]
+    fn test_part2() {
+        assert_eq!(part2(&generator(INPUT)), 12);
+        assert_eq!(part2_bresenham(&generator(INPUT)), 12);
+        assert_eq!(part2_sweep(&generator(INPUT)), 12);
+    }
+
+    #[test]
+    fn test_heat_map() {
+        let heat_map = HeatMap::new(&generator(INPUT), |_| true, Rasterization::Exact);
+        assert_eq!(
+            heat_map.to_string(),
+            "1.1....11.\n\
+             .111...2..\n\
+             ..2.1.111.\n\
+             ...1.2.2..\n\
+             .112313211\n\
+             ...1.2....\n\
+             ..1...1...\n\
+             .1.....1..\n\
+             1.......1.\n\
+             222111....\n"
+        );
+        assert_eq!(
+            heat_map.histogram(),
+            BTreeMap::from([(0, 61), (1, 27), (2, 10), (3, 2)])
+        );
+        assert_eq!(
+            heat_map.hottest_points(),
+            (3, vec![MapPoint::new(4, 4), MapPoint::new(6, 4)])
+        );
+        assert!(heat_map
+            .to_pgm()
+            .starts_with("P2\n10 10\n3\n1 0 1 0 0 0 0 1 1 0\n"));
+
+        let straight = HeatMap::new(&generator(INPUT), is_straight, Rasterization::Exact);
+        assert_eq!(
+            straight.to_string(),
+            ".......1..\n\
+             ..1....1..\n\
+             ..1....1..\n\
+             .......1..\n\
+             .112111211\n\
+             ..........\n\
+             ..........\n\
+             ..........\n\
+             ..........\n\
+             222111....\n"
+        );
+    }
+
+    #[test]
+    fn test_general_lines() {
+        let line = Line::new(Point::new((0, 0)), Point::new((6, 3)));
+        assert!(matches!(line, Line::General { .. }));
+        assert_eq!(
+            line.points(Rasterization::Exact),
+            vec![(0, 0), (2, 1), (4, 2), (6, 3)]
+        );
+        assert_eq!(
+            line.points(Rasterization::Bresenham),
+            vec![(0, 0), (1, 1), (2, 1), (3, 2), (4, 2), (5, 3), (6, 3)]
+        );
+
+        // Steep and reversed, with no lattice points between the ends
+        let line = Line::new(Point::new((2, 5)), Point::new((1, 0)));
+        assert_eq!(line.points(Rasterization::Exact), vec![(2, 5), (1, 0)]);
+        assert_eq!(
+            line.points(Rasterization::Bresenham),
+            vec![(2, 5), (2, 4), (2, 3), (1, 2), (1, 1), (1, 0)]
+        );
+    }
+
+    #[test]
+    fn test_general_overlaps() {
+        let input = generator("0,0 -> 6,3\n0,1 -> 6,1\n4,0 -> 4,4");
+        // Besides where the straight lines cross, the exact 2:1 line only meets them at (2, 1) and (4, 2),
+        // while the Bresenham one also covers (1, 1)
+        assert_eq!(part2(&input), 3);
+        assert_eq!(part2_bresenham(&input), 4);
+        assert_eq!(part2_sweep(&input), 3);
+    }
+
+    #[test]
+    fn test_sweep_matches_grid() {
+        // Lines at every kind of angle, including collinear runs in both directions, single points and
+        // parallel lines that never touch
+        let input = generator(
+            "0,0 -> 12,12\n12,12 -> 4,4\n0,6 -> 12,0\n2,5 -> 10,1\n8,3 -> 8,11\n3,3 -> 3,3\n\
+             1,11 -> 11,1\n0,9 -> 12,9\n4,9 -> 4,9\n1,0 -> 7,9\n5,0 -> 11,9\n0,2 -> 12,8\n6,2 -> 10,4",
+        );
+        let grid = HeatMap::new(&input, |_| true, Rasterization::Exact).count_overlaps();
+        assert_eq!(part2_sweep(&input), grid);
+        assert_eq!(part1_sweep(&input), part1(&input));
+    }
+
+    #[test]
+    fn test_huge_coordinates() {
+        // Far too spread out for a dense grid, but only a handful of lines
+        let input = generator(
+            "0,0 -> 4000000000,4000000000\n\
+             4000000000,0 -> 0,4000000000\n\
+             0,3000000000 -> 4000000000,3000000000",
+        );
+        assert_eq!(part2_sweep(&input), 3);
+
+        // More overlapping points than fit in a u16
+        let input = generator("0,0 -> 99999,0\n99999,0 -> 0,0");
+        assert_eq!(part1_sweep(&input), 100000);
+        assert_eq!(part1(&input), 100000);
+    }
+
+    #[test]
+    fn test_huge_general_lines() {
+        // Steps this long overflow 64 bit cross products. The first and third lines only have their ends as
+        // lattice points, while the 2:1 line has a lattice point in every other column.
+        let input = generator(
+            "0,0 -> 4000000000,3999999999\n\
+             0,1 -> 4000000000,1\n\
+             4000000000,0 -> 0,3999999999\n\
+             4000000000,3999999999 -> 4000000000,3999999999\n\
+             0,3999999999 -> 4294967295,3999999999\n\
+             1,0 -> 4294967295,2147483647\n\
+             4294967295,0 -> 4294967295,4294967295",
+        );
+        assert_eq!(input.0, 1 << 32);
+        assert_eq!(part2_sweep(&input), 5);
+    }
+
+    #[test]
+    fn test_huge_collinear_overlaps() {
+        // Billions of shared points along each line, where the runs on the two straight lines meet at (5, 0)
+        // and the 2:1 line only starts overlapping halfway along
+        let input = generator(
+            "0,0 -> 4294967295,0\n\
+             4294967295,0 -> 0,0\n\
+             5,0 -> 5,4000000000\n\
+             5,4000000000 -> 5,0\n\
+             0,0 -> 4000000000,2000000000\n\
+             2000000000,1000000000 -> 4294967294,2147483647",
+        );
+        assert_eq!(part1_sweep(&input), 4294967296 + 4000000001 - 1);
+        assert_eq!(
+            part2_sweep(&input),
+            4294967296 + 4000000001 - 1 + 1000000001
+        );
+    }
 }
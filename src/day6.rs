@@ -1,46 +1,373 @@
+use std::fmt::Display;
+use std::ops::{Add, Mul};
+
 use aoc_runner_derive::{aoc, aoc_generator};
+use itertools::Itertools;
+use num_traits::{One, Zero};
 
-#[aoc_generator(day6)]
-pub fn generator(input: &str) -> Vec<usize> {
-    input
-        .split(",")
-        .map(str::parse::<usize>)
-        .flatten()
+// Anything that can count fish: plain integers, big integers, or integers modulo some number
+pub trait Count: Clone + Zero + One {}
+
+impl<T> Count for T where T: Clone + Zero + One {}
+
+// Counts modulo `M`, for when only the remainder of an astronomically large population is needed. Any
+// arithmetic with `M` set to 0 fails to compile.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Modular<const M: u64>(pub u64);
+
+impl<const M: u64> Modular<M> {
+    fn reduce(value: u128) -> Self {
+        const { assert!(M > 0, "Can't count modulo 0") };
+        Self((value % M as u128) as u64)
+    }
+}
+
+impl<const M: u64> Add for Modular<M> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::reduce(self.0 as u128 + other.0 as u128)
+    }
+}
+
+impl<const M: u64> Mul for Modular<M> {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self::reduce(self.0 as u128 * other.0 as u128)
+    }
+}
+
+impl<const M: u64> Zero for Modular<M> {
+    fn zero() -> Self {
+        Self(0)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl<const M: u64> One for Modular<M> {
+    fn one() -> Self {
+        Self::reduce(1)
+    }
+}
+
+type Matrix<T> = Vec<Vec<T>>;
+
+fn multiply<T: Count>(a: &Matrix<T>, b: &Matrix<T>) -> Matrix<T> {
+    (0..a.len())
+        .map(|row| {
+            (0..b[0].len())
+                .map(|column| {
+                    (0..b.len()).fold(T::zero(), |sum, i| {
+                        sum + a[row][i].clone() * b[i][column].clone()
+                    })
+                })
+                .collect()
+        })
         .collect()
 }
 
-fn simulate_and_count(initial_stages: &[usize], num_days: usize) -> u64 {
-    // This is where newly hatched lanternfish live until they're on to the "normal" mature lifecycle
-    let mut immature = [0u64; 2];
-    // Circular buffer style counter of number of laternfish at each "age"
-    let mut mature = [0u64; 7];
+// When a fish's timer passes 0 it goes back to `reset_timer` and a new fish starts at `newborn_timer`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Lifecycle {
+    pub reset_timer: usize,
+    pub newborn_timer: usize,
+}
 
-    // Count up the fish from the input data into their appropriate buckets
-    initial_stages.into_iter().for_each(|stage| {
-        mature[*stage] += 1;
-    });
+impl Lifecycle {
+    pub const LANTERNFISH: Self = Self {
+        reset_timer: 6,
+        newborn_timer: 8,
+    };
 
-    for current_day in 0..num_days {
-        // Treat `immature` as a circular buffer for who is ready to graduate into the mature bucket
-        let current_immature_bucket = current_day % 2;
-        let graduating_immature = immature[current_immature_bucket];
+    // Number of fish at each timer value, with room for the highest timer any fish can have
+    fn histogram<T: Count>(&self, initial_timers: &[usize]) -> Vec<T> {
+        let max_timer = initial_timers
+            .iter()
+            .copied()
+            .chain([self.reset_timer, self.newborn_timer])
+            .max()
+            .unwrap();
+        let mut histogram = vec![T::zero(); max_timer + 1];
+        for timer in initial_timers {
+            histogram[*timer] = histogram[*timer].clone() + T::one();
+        }
+        histogram
+    }
 
-        let current_stage = current_day % 7;
-        // "Spawn" immature lanternfish from today's generation
-        immature[current_immature_bucket] = mature[current_stage];
-        // Graduate the previously counted immature into the mature array
-        mature[current_stage] += graduating_immature;
+    // The population on every day, starting with the initial one on day 0
+    pub fn days<T: Count>(&self, initial_timers: &[usize]) -> Days<T> {
+        Days {
+            lifecycle: *self,
+            histogram: self.histogram(initial_timers),
+            day: 0,
+        }
     }
 
-    mature.iter().sum::<u64>() + immature.iter().sum::<u64>()
+    pub fn simulate<T: Count>(&self, initial_timers: &[usize], num_days: usize) -> T {
+        self.days(initial_timers).nth(num_days).unwrap().total
+    }
+
+    // The matrix taking one day's histogram to the next
+    fn transition<T: Count>(&self, num_timers: usize) -> Matrix<T> {
+        let mut matrix = vec![vec![T::zero(); num_timers]; num_timers];
+        for timer in 1..num_timers {
+            matrix[timer - 1][timer] = T::one();
+        }
+        for timer in [self.reset_timer, self.newborn_timer] {
+            matrix[timer][0] = matrix[timer][0].clone() + T::one();
+        }
+        matrix
+    }
+
+    // Raises the transition matrix to the number of days by repeated squaring, so it only takes a few dozen
+    // matrix multiplications even for billions of days
+    pub fn count_by_matrix<T: Count>(&self, initial_timers: &[usize], num_days: u64) -> T {
+        let histogram = self.histogram::<T>(initial_timers);
+        let num_timers = histogram.len();
+
+        let mut power = self.transition::<T>(num_timers);
+        let mut result = (0..num_timers)
+            .map(|row| {
+                (0..num_timers)
+                    .map(|column| if row == column { T::one() } else { T::zero() })
+                    .collect()
+            })
+            .collect::<Matrix<T>>();
+        let mut remaining_days = num_days;
+        while remaining_days > 0 {
+            if remaining_days & 1 == 1 {
+                result = multiply(&result, &power);
+            }
+            power = multiply(&power, &power);
+            remaining_days >>= 1;
+        }
+
+        let column = histogram.into_iter().map(|count| vec![count]).collect();
+        multiply(&result, &column)
+            .into_iter()
+            .fold(T::zero(), |total, row| total + row[0].clone())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Day<T> {
+    pub day: usize,
+    // Number of fish with each timer value, starting from 0
+    pub histogram: Vec<T>,
+    pub total: T,
+}
+
+// Steps through the days by treating the histogram as a circular buffer, so only the spawning fish need to
+// be touched
+pub struct Days<T> {
+    lifecycle: Lifecycle,
+    // Timer `t` is at index `(day + t) % len`
+    histogram: Vec<T>,
+    day: usize,
+}
+
+impl<T: Count> Iterator for Days<T> {
+    type Item = Day<T>;
+
+    fn next(&mut self) -> Option<Day<T>> {
+        let len = self.histogram.len();
+        let zero = self.day % len;
+        let histogram = (0..len)
+            .map(|timer| self.histogram[(zero + timer) % len].clone())
+            .collect::<Vec<_>>();
+        let total = histogram
+            .iter()
+            .fold(T::zero(), |total, count| total + count.clone());
+        let day = Day {
+            day: self.day,
+            histogram,
+            total,
+        };
+
+        // The slot holding today's timer 0 holds the highest timer tomorrow, so empty it out and add its fish
+        // back in relative to tomorrow's timer 0
+        let spawning = std::mem::replace(&mut self.histogram[zero], T::zero());
+        for timer in [self.lifecycle.reset_timer, self.lifecycle.newborn_timer] {
+            let slot = (zero + 1 + timer) % len;
+            self.histogram[slot] = self.histogram[slot].clone() + spawning.clone();
+        }
+        self.day += 1;
+
+        Some(day)
+    }
+}
+
+// One row per day with the count for each timer value and the total, for plotting
+pub fn to_csv<T, I>(days: I) -> String
+where
+    T: Display,
+    I: IntoIterator<Item = Day<T>>,
+{
+    let mut days = days.into_iter().peekable();
+    let num_timers = days.peek().map_or(0, |day| day.histogram.len());
+    let mut csv = format!(
+        "day,{}total\n",
+        (0..num_timers)
+            .map(|timer| format!("timer_{},", timer))
+            .join("")
+    );
+    for day in days {
+        csv.push_str(&format!(
+            "{},{}{}\n",
+            day.day,
+            day.histogram
+                .iter()
+                .map(|count| format!("{},", count))
+                .join(""),
+            day.total
+        ));
+    }
+    csv
+}
+
+#[aoc_generator(day6)]
+pub fn generator(input: &str) -> Vec<usize> {
+    input
+        .split(',')
+        .filter_map(|timer| timer.trim().parse::<usize>().ok())
+        .collect()
 }
 
 #[aoc(day6, part1)]
-pub fn part1(initial_stages: &[usize]) -> u64 {
-    simulate_and_count(initial_stages, 80)
+pub fn part1(initial_timers: &[usize]) -> u64 {
+    Lifecycle::LANTERNFISH.simulate(initial_timers, 80)
 }
 
 #[aoc(day6, part2)]
-pub fn part2(initial_stages: &[usize]) -> u64 {
-    simulate_and_count(initial_stages, 256)
+pub fn part2(initial_timers: &[usize]) -> u64 {
+    Lifecycle::LANTERNFISH.simulate(initial_timers, 256)
+}
+
+#[aoc(day6, part2, Matrix)]
+pub fn part2_matrix(initial_timers: &[usize]) -> u64 {
+    Lifecycle::LANTERNFISH.count_by_matrix(initial_timers, 256)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigUint;
+
+    const INPUT: &str = "3,4,3,1,2";
+
+    #[test]
+    fn test_part1() {
+        assert_eq!(
+            Lifecycle::LANTERNFISH.simulate::<u64>(&generator(INPUT), 18),
+            26
+        );
+        assert_eq!(part1(&generator(INPUT)), 5934);
+    }
+
+    #[test]
+    fn test_part2() {
+        assert_eq!(part2(&generator(INPUT)), 26984457539);
+        assert_eq!(part2_matrix(&generator(INPUT)), 26984457539);
+    }
+
+    // Every fish individually, the way the puzzle describes it
+    fn simulate_naive(
+        lifecycle: Lifecycle,
+        initial_timers: &[usize],
+        num_days: usize,
+    ) -> Vec<usize> {
+        let mut timers = initial_timers.to_vec();
+        for _ in 0..num_days {
+            let num_spawning = timers.iter().filter(|timer| **timer == 0).count();
+            for timer in timers.iter_mut() {
+                *timer = timer.checked_sub(1).unwrap_or(lifecycle.reset_timer);
+            }
+            timers.extend(std::iter::repeat_n(lifecycle.newborn_timer, num_spawning));
+        }
+        timers
+    }
+
+    #[test]
+    fn test_days_match_naive() {
+        let lifecycles = [
+            Lifecycle::LANTERNFISH,
+            Lifecycle {
+                reset_timer: 4,
+                newborn_timer: 1,
+            },
+            Lifecycle {
+                reset_timer: 3,
+                newborn_timer: 3,
+            },
+        ];
+        for lifecycle in lifecycles {
+            let timers = generator(INPUT);
+            for day in lifecycle.days::<u64>(&timers).take(40) {
+                let naive = simulate_naive(lifecycle, &timers, day.day);
+                assert_eq!(day.total, naive.len() as u64);
+                for (timer, count) in day.histogram.iter().enumerate() {
+                    let naive_count = naive.iter().filter(|t| **t == timer).count();
+                    assert_eq!(*count, naive_count as u64);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_csv() {
+        let lifecycle = Lifecycle {
+            reset_timer: 1,
+            newborn_timer: 2,
+        };
+        assert_eq!(
+            to_csv(lifecycle.days::<u64>(&[0, 2]).take(4)),
+            "day,timer_0,timer_1,timer_2,total\n\
+             0,1,0,1,2\n\
+             1,0,2,1,3\n\
+             2,2,1,0,3\n\
+             3,1,2,2,5\n"
+        );
+    }
+
+    #[test]
+    fn test_other_lifecycles() {
+        // Newborns that are ready sooner than their parents, and timers above both lifecycle timers
+        let lifecycle = Lifecycle {
+            reset_timer: 4,
+            newborn_timer: 1,
+        };
+        let timers = [0, 7, 2];
+        for num_days in [0, 1, 5, 20, 63] {
+            assert_eq!(
+                lifecycle.count_by_matrix::<u64>(&timers, num_days as u64),
+                lifecycle.simulate::<u64>(&timers, num_days)
+            );
+        }
+    }
+
+    #[test]
+    fn test_big_counts() {
+        const MODULUS: u64 = 1_000_000_007;
+        let timers = generator(INPUT);
+        // Far beyond what fits in a u64
+        let big = Lifecycle::LANTERNFISH.simulate::<BigUint>(&timers, 1000);
+        assert_eq!(
+            Lifecycle::LANTERNFISH.count_by_matrix::<BigUint>(&timers, 1000),
+            big
+        );
+        assert_eq!(
+            Lifecycle::LANTERNFISH.count_by_matrix::<Modular<MODULUS>>(&timers, 1000),
+            Modular((big % MODULUS).try_into().unwrap())
+        );
+
+        // The matrix only needs a few dozen multiplications for a million days
+        assert_eq!(
+            Lifecycle::LANTERNFISH.count_by_matrix::<Modular<MODULUS>>(&timers, 1_000_000),
+            Lifecycle::LANTERNFISH.simulate::<Modular<MODULUS>>(&timers, 1_000_000)
+        );
+    }
 }
@@ -1,53 +1,389 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 
+// How much fuel a crab uses to move a given distance. The puzzle itself only uses the linear and triangular
+// costs.
+pub enum CostFunction {
+    // One unit per step
+    Linear,
+    // Each step costs one more than the last
+    Triangular,
+    // The square of the distance
+    Quadratic,
+    // Anything else. Only convex costs that never decrease with distance are guaranteed to be optimized
+    // correctly.
+    Custom(Box<dyn Fn(i64) -> i64>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Crab {
+    pub position: i64,
+    // Multiplies the fuel this crab uses, so less efficient crabs count for more
+    pub weight: i64,
+}
+
+impl Crab {
+    pub fn new(position: i64) -> Self {
+        Self {
+            position,
+            weight: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Alignment {
+    pub position: i64,
+    pub cost: i64,
+}
+
+// Several meeting points, each one shared by a group of neighbouring crabs
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeetingPlan {
+    // From left to right
+    pub targets: Vec<i64>,
+    pub cost: i64,
+}
+
+impl CostFunction {
+    pub fn cost(&self, distance: i64) -> i64 {
+        match self {
+            Self::Linear => distance,
+            Self::Triangular => distance * (distance + 1) / 2,
+            Self::Quadratic => distance * distance,
+            Self::Custom(cost) => cost(distance),
+        }
+    }
+
+    pub fn total_cost(&self, crabs: &[Crab], target: i64) -> i64 {
+        crabs
+            .iter()
+            .map(|crab| crab.weight * self.cost((crab.position - target).abs()))
+            .sum()
+    }
+
+    fn align(&self, crabs: &[Crab], target: i64) -> Alignment {
+        Alignment {
+            position: target,
+            cost: self.total_cost(crabs, target),
+        }
+    }
+
+    // Best of the given targets, preferring the leftmost on ties
+    fn best_of<I>(&self, crabs: &[Crab], targets: I) -> Option<Alignment>
+    where
+        I: IntoIterator<Item = i64>,
+    {
+        targets
+            .into_iter()
+            .map(|target| self.align(crabs, target))
+            .min_by_key(|alignment| (alignment.cost, alignment.position))
+    }
+
+    // Try every position between the leftmost and rightmost crab
+    pub fn brute_force(&self, crabs: &[Crab]) -> Option<Alignment> {
+        let min = crabs.iter().map(|crab| crab.position).min()?;
+        let max = crabs.iter().map(|crab| crab.position).max()?;
+        self.best_of(crabs, min..=max)
+    }
+
+    // The total of convex costs is itself convex, so its slope only ever goes up. Binary search for the first
+    // position where moving right stops helping.
+    pub fn convex_search(&self, crabs: &[Crab]) -> Option<Alignment> {
+        let mut low = crabs.iter().map(|crab| crab.position).min()?;
+        let mut high = crabs.iter().map(|crab| crab.position).max()?;
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if self.total_cost(crabs, middle + 1) >= self.total_cost(crabs, middle) {
+                high = middle;
+            } else {
+                low = middle + 1;
+            }
+        }
+        Some(self.align(crabs, low))
+    }
+
+    // Uses what's known about the built-in costs to only check a handful of targets, falling back to the
+    // convex search for custom costs
+    pub fn optimize(&self, crabs: &[Crab]) -> Option<Alignment> {
+        let total_weight = crabs.iter().map(|crab| crab.weight).sum::<i64>();
+        match self {
+            // Any point where neither side outweighs the other is optimal, so the lower weighted median is
+            // enough
+            Self::Linear => {
+                let mut sorted = crabs.to_vec();
+                sorted.sort_unstable_by_key(|crab| crab.position);
+                let mut weight_so_far = 0;
+                let median = sorted.iter().find(|crab| {
+                    weight_so_far += crab.weight;
+                    weight_so_far * 2 >= total_weight
+                })?;
+                Some(self.align(crabs, median.position))
+            }
+            // The squared distances are minimized at the weighted mean, and the triangular cost is half that
+            // plus half the linear cost, which can only move the optimum by less than half a step
+            Self::Triangular | Self::Quadratic if total_weight > 0 => {
+                let mean = crabs
+                    .iter()
+                    .map(|crab| crab.weight * crab.position)
+                    .sum::<i64>()
+                    .div_euclid(total_weight);
+                self.best_of(crabs, mean - 1..=mean + 1)
+            }
+            _ => self.convex_search(crabs),
+        }
+    }
+
+    // Split the crabs into at most `k` groups that each meet at their own point. Since costs only grow with
+    // distance, every group in an optimal plan is a run of neighbouring crabs, so this tries every way of
+    // cutting the sorted crabs into runs.
+    pub fn meeting_points(&self, crabs: &[Crab], k: usize) -> Option<MeetingPlan> {
+        let mut sorted = crabs.to_vec();
+        sorted.sort_unstable_by_key(|crab| crab.position);
+        let n = sorted.len();
+        let k = k.min(n);
+        if k == 0 {
+            return None;
+        }
+
+        // `groups[start][end - start - 1]` is the best single meeting point for the crabs in `start..end`
+        let groups = (0..n)
+            .map(|start| {
+                (start + 1..=n)
+                    .map(|end| self.optimize(&sorted[start..end]).unwrap())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let group = |start: usize, end: usize| groups[start][end - start - 1];
+
+        // `best[g][end]` is the cheapest way for the first `end` crabs to meet in `g` groups, along with where
+        // the last of those groups starts
+        let mut best = vec![vec![None; n + 1]; k + 1];
+        best[0][0] = Some((0, 0));
+        for g in 1..=k {
+            for end in g..=n {
+                best[g][end] = (g - 1..end)
+                    .filter_map(|start| {
+                        let (cost, _) = best[g - 1][start]?;
+                        Some((cost + group(start, end).cost, start))
+                    })
+                    .min();
+            }
+        }
+
+        let (cost, _) = best[k][n]?;
+        let mut targets = Vec::with_capacity(k);
+        let mut end = n;
+        for g in (1..=k).rev() {
+            let (_, start) = best[g][end]?;
+            targets.push(group(start, end).position);
+            end = start;
+        }
+        targets.reverse();
+
+        Some(MeetingPlan { targets, cost })
+    }
+}
+
+// Each crab is either just a position, or a position and weight as `position:weight`
 #[aoc_generator(day7)]
-pub fn generator(input: &str) -> Vec<i32> {
-    input.split(",").map(str::parse::<i32>).flatten().collect()
+pub fn generator(input: &str) -> Vec<Crab> {
+    input
+        .split(',')
+        .filter_map(|crab| match crab.trim().split_once(':') {
+            Some((position, weight)) => Some(Crab {
+                position: position.parse().ok()?,
+                weight: weight.parse().ok().filter(|weight| *weight >= 0)?,
+            }),
+            None => Some(Crab::new(crab.trim().parse().ok()?)),
+        })
+        .collect()
 }
 
 #[aoc(day7, part1)]
-pub fn part1(horizontal_positions: &Vec<i32>) -> i32 {
-    let horizontal_positions = {
-        let mut positions = horizontal_positions.clone();
-        positions.sort();
-        positions
-    };
+pub fn part1(crabs: &[Crab]) -> Option<i64> {
+    Some(CostFunction::Linear.optimize(crabs)?.cost)
+}
 
-    let median = if horizontal_positions.len() % 2 != 0 {
-        (horizontal_positions[horizontal_positions.len() / 2]
-            + horizontal_positions[horizontal_positions.len() / 2 + 1])
-            / 2
-    } else {
-        horizontal_positions[horizontal_positions.len() / 2]
-    };
+#[aoc(day7, part2)]
+pub fn part2(crabs: &[Crab]) -> Option<i64> {
+    Some(CostFunction::Triangular.optimize(crabs)?.cost)
+}
 
-    let cost = horizontal_positions
-        .iter()
-        .map(|x| (x - median).abs())
-        .sum::<i32>();
+#[aoc(day7, part2, ConvexSearch)]
+pub fn part2_convex_search(crabs: &[Crab]) -> Option<i64> {
+    Some(CostFunction::Triangular.convex_search(crabs)?.cost)
+}
 
-    cost
+#[aoc(day7, part2, BruteForce)]
+pub fn part2_brute_force(crabs: &[Crab]) -> Option<i64> {
+    Some(CostFunction::Triangular.brute_force(crabs)?.cost)
 }
 
-#[aoc(day7, part2)]
-pub fn part2(horizontal_positions: &Vec<i32>) -> Option<i32> {
-    let horizontal_positions = {
-        let mut positions = horizontal_positions.clone();
-        positions.sort();
-        positions
-    };
-
-    let brute_force_options =
-        horizontal_positions[0]..=horizontal_positions[horizontal_positions.len() - 1];
-    let costs = brute_force_options.map(|position| {
-        horizontal_positions
-            .iter()
-            .map(|x| {
-                let num_moves = (x - position).abs();
-                (num_moves * (num_moves + 1)) / 2
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "16,1,2,0,4,2,7,1,2,14";
+
+    fn crabs(positions: &[i64]) -> Vec<Crab> {
+        positions.iter().copied().map(Crab::new).collect()
+    }
+
+    #[test]
+    fn test_part1() {
+        assert_eq!(part1(&generator(INPUT)), Some(37));
+        assert_eq!(
+            CostFunction::Linear.optimize(&generator(INPUT)),
+            Some(Alignment {
+                position: 2,
+                cost: 37
+            })
+        );
+    }
+
+    #[test]
+    fn test_part2() {
+        assert_eq!(part2(&generator(INPUT)), Some(168));
+        assert_eq!(part2_convex_search(&generator(INPUT)), Some(168));
+        assert_eq!(part2_brute_force(&generator(INPUT)), Some(168));
+        assert_eq!(
+            CostFunction::Triangular.optimize(&generator(INPUT)),
+            Some(Alignment {
+                position: 5,
+                cost: 168
+            })
+        );
+    }
+
+    #[test]
+    fn test_median() {
+        // An even number of crabs, where the old median read past the middle
+        assert_eq!(part1(&crabs(&[1, 2, 10, 11])), Some(18));
+        assert_eq!(part1(&crabs(&[5])), Some(0));
+        assert_eq!(part1(&[]), None);
+        assert_eq!(part2(&[]), None);
+    }
+
+    #[test]
+    fn test_against_brute_force() {
+        // Simple linear congruential generator, so the crabs are the same on every run
+        let mut seed = 12345u64;
+        let mut next = |bound: u64| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) % bound
+        };
+
+        let cost_functions = [
+            CostFunction::Linear,
+            CostFunction::Triangular,
+            CostFunction::Quadratic,
+            CostFunction::Custom(Box::new(|distance| distance * distance * distance)),
+            CostFunction::Custom(Box::new(|distance| (distance - 3).max(0))),
+        ];
+        for _ in 0..50 {
+            let num_crabs = next(20) as usize + 1;
+            let crabs = (0..num_crabs)
+                .map(|_| Crab {
+                    position: next(100) as i64 - 20,
+                    weight: next(5) as i64 + 1,
+                })
+                .collect::<Vec<_>>();
+            for cost_function in &cost_functions {
+                let expected = cost_function.brute_force(&crabs).unwrap().cost;
+                assert_eq!(cost_function.optimize(&crabs).unwrap().cost, expected);
+                assert_eq!(cost_function.convex_search(&crabs).unwrap().cost, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_weights() {
+        let crabs = generator("0:5,10,20");
+        assert_eq!(
+            crabs[0],
+            Crab {
+                position: 0,
+                weight: 5
+            }
+        );
+        // The heavy crab outweighs the other two, so everyone comes to it
+        assert_eq!(
+            CostFunction::Linear.optimize(&crabs),
+            Some(Alignment {
+                position: 0,
+                cost: 30
+            })
+        );
+        assert_eq!(
+            CostFunction::Quadratic.optimize(&crabs),
+            Some(Alignment {
+                position: 4,
+                cost: 5 * 16 + 36 + 256
+            })
+        );
+    }
+
+    #[test]
+    fn test_meeting_points() {
+        let crabs = generator("0,1,2,10,11,12,30");
+        assert_eq!(
+            CostFunction::Linear.meeting_points(&crabs, 2),
+            Some(MeetingPlan {
+                targets: vec![1, 11],
+                cost: 2 + 2 + 19
+            })
+        );
+        assert_eq!(
+            CostFunction::Linear.meeting_points(&crabs, 3),
+            Some(MeetingPlan {
+                targets: vec![1, 11, 30],
+                cost: 4
             })
-            .sum::<i32>()
-    });
+        );
+        // One meeting point is the same as aligning everyone, and enough of them costs nothing
+        assert_eq!(
+            CostFunction::Triangular
+                .meeting_points(&generator(INPUT), 1)
+                .map(|plan| plan.cost),
+            Some(168)
+        );
+        assert_eq!(
+            CostFunction::Triangular
+                .meeting_points(&crabs, 10)
+                .map(|plan| plan.cost),
+            Some(0)
+        );
+        assert_eq!(CostFunction::Linear.meeting_points(&crabs, 0), None);
+    }
 
-    costs.min()
+    #[test]
+    fn test_meeting_points_against_brute_force() {
+        // Every pair of targets, with each crab going to whichever is cheaper for it
+        let crabs = generator("3:2,9,-4,15:3,7,8:4,0,21");
+        let cost_function = CostFunction::Triangular;
+        let mut expected = i64::MAX;
+        for first in -4..=21 {
+            for second in first..=21 {
+                let cost = crabs
+                    .iter()
+                    .map(|crab| {
+                        [first, second]
+                            .iter()
+                            .map(|target| {
+                                crab.weight * cost_function.cost((crab.position - target).abs())
+                            })
+                            .min()
+                            .unwrap()
+                    })
+                    .sum::<i64>();
+                expected = expected.min(cost);
+            }
+        }
+        assert_eq!(
+            cost_function.meeting_points(&crabs, 2).unwrap().cost,
+            expected
+        );
+    }
 }
@@ -1,132 +1,516 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 
+use anyhow::{anyhow, bail, Result};
 use itertools::Itertools;
 
-fn parse_wire(c: char) -> Option<u8> {
-    match c {
-        'a' => Some(0b0000001),
-        'b' => Some(0b0000010),
-        'c' => Some(0b0000100),
-        'd' => Some(0b0001000),
-        'e' => Some(0b0010000),
-        'f' => Some(0b0100000),
-        'g' => Some(0b1000000),
-        _ => None,
+// Wires and segments are both named by letters, starting from `a` for bit 0
+fn parse_pattern(s: &str) -> Result<u32> {
+    s.chars().try_fold(0, |mask, c| match c {
+        'a'..='z' => Ok(mask | 1 << (c as u32 - 'a' as u32)),
+        _ => bail!("Invalid wire '{}' in pattern: {}", c, s),
+    })
+}
+
+// The segments that light up for each glyph of a display
+#[derive(Debug, Clone, PartialEq)]
+pub struct Font {
+    pub num_segments: usize,
+    pub glyphs: Vec<(char, u32)>,
+}
+
+impl Font {
+    pub fn new(num_segments: usize, glyphs: &[(char, &str)]) -> Self {
+        let glyphs = glyphs
+            .iter()
+            .map(|(glyph, segments)| {
+                let segments = parse_pattern(segments).unwrap();
+                assert!(
+                    segments >> num_segments == 0,
+                    "Glyph '{}' uses more than {} segments",
+                    glyph,
+                    num_segments
+                );
+                (*glyph, segments)
+            })
+            .collect();
+        Self {
+            num_segments,
+            glyphs,
+        }
+    }
+
+    // Segments `a` to `g` go clockwise from the top and end with the middle. That's not the lettering from the
+    // puzzle's diagram, but it's the usual one and the bigger displays build on it:
+    //
+    //  aaaa
+    // f    b
+    // f    b
+    //  gggg
+    // e    c
+    // e    c
+    //  dddd
+    pub fn seven_segment() -> Self {
+        Self::new(7, &Self::SEVEN_SEGMENT_DIGITS)
+    }
+
+    const SEVEN_SEGMENT_DIGITS: [(char, &'static str); 10] = [
+        ('0', "abcdef"),
+        ('1', "bc"),
+        ('2', "abdeg"),
+        ('3', "abcdg"),
+        ('4', "bcfg"),
+        ('5', "acdfg"),
+        ('6', "acdefg"),
+        ('7', "abc"),
+        ('8', "abcdefg"),
+        ('9', "abcdfg"),
+    ];
+
+    pub fn glyph_for(&self, segments: u32) -> Option<char> {
+        self.glyphs
+            .iter()
+            .find(|(_, glyph_segments)| *glyph_segments == segments)
+            .map(|(glyph, _)| *glyph)
+    }
+
+    fn glyphs_with_size(&self, size: u32) -> impl Iterator<Item = u32> + '_ {
+        self.glyphs
+            .iter()
+            .map(|(_, segments)| *segments)
+            .filter(move |segments| segments.count_ones() == size)
     }
 }
 
-trait SevenSegment {
-    fn find_and_remove_digit<T>(&mut self, predicate: T) -> u8
-    where
-        T: FnMut(u8) -> bool;
+// Other displays, for notes that don't come from the puzzle
+impl Font {
+    pub fn seven_segment_hex() -> Self {
+        let mut font = Self::seven_segment();
+        font.glyphs.extend(
+            Self::new(
+                7,
+                &[
+                    ('A', "abcefg"),
+                    ('b', "cdefg"),
+                    ('C', "adef"),
+                    ('d', "bcdeg"),
+                    ('E', "adefg"),
+                    ('F', "aefg"),
+                ],
+            )
+            .glyphs,
+        );
+        font
+    }
+
+    // Digits and capital letters. On top of the seven segments, `g` is the left half of the middle bar and
+    // `h` the right half, `i`, `j` and `k` are the upper diagonal, vertical and diagonal from left to right,
+    // and `l`, `m` and `n` are the lower ones:
+    //
+    //  aaaaa
+    // fi j kb
+    // f ijk b
+    //  gg hh
+    // e lmn c
+    // el m nc
+    //  ddddd
+    pub fn fourteen_segment() -> Self {
+        Self::new(14, &Self::FOURTEEN_SEGMENT_GLYPHS)
+    }
+
+    const FOURTEEN_SEGMENT_GLYPHS: [(char, &'static str); 36] = [
+        ('0', "abcdefkl"),
+        ('1', "bc"),
+        ('2', "abdegh"),
+        ('3', "abcdh"),
+        ('4', "bcfgh"),
+        ('5', "adfgn"),
+        ('6', "acdefgh"),
+        ('7', "abc"),
+        ('8', "abcdefgh"),
+        ('9', "abcdfgh"),
+        ('A', "abcefgh"),
+        ('B', "abcdhjm"),
+        ('C', "adef"),
+        ('D', "abcdjm"),
+        ('E', "adefgh"),
+        ('F', "aefg"),
+        ('G', "acdefh"),
+        ('H', "bcefgh"),
+        ('I', "adjm"),
+        ('J', "bcde"),
+        ('K', "efgkn"),
+        ('L', "def"),
+        ('M', "bcefik"),
+        ('N', "bcefin"),
+        ('O', "abcdef"),
+        ('P', "abefgh"),
+        ('Q', "abcdefn"),
+        ('R', "abefghn"),
+        ('S', "acdfgh"),
+        ('T', "ajm"),
+        ('U', "bcdef"),
+        ('V', "efkl"),
+        ('W', "bcefln"),
+        ('X', "ikln"),
+        ('Y', "ikm"),
+        ('Z', "adkl"),
+    ];
+
+    // The fourteen segment glyphs with the top and bottom bars split in two, where `a` and `d` are now the
+    // left halves and `o` and `p` the right halves, plus a few small glyphs that only use the left halves
+    pub fn sixteen_segment() -> Self {
+        let split = |segments: &str| segments.replace('a', "ao").replace('d', "dp");
+        let glyphs = Self::FOURTEEN_SEGMENT_GLYPHS
+            .iter()
+            .map(|(glyph, segments)| (*glyph, split(segments)))
+            .chain(
+                [
+                    ('c', "deg"),
+                    ('n', "egm"),
+                    ('o', "degm"),
+                    ('r', "eg"),
+                    ('u', "dem"),
+                    ('°', "afgj"),
+                ]
+                .map(|(glyph, segments)| (glyph, segments.to_owned())),
+            )
+            .collect::<Vec<_>>();
+        Self::new(
+            16,
+            &glyphs
+                .iter()
+                .map(|(glyph, segments)| (*glyph, segments.as_str()))
+                .collect::<Vec<_>>(),
+        )
+    }
 }
 
-impl SevenSegment for Vec<u8> {
-    fn find_and_remove_digit<T>(&mut self, mut predicate: T) -> u8
-    where
-        T: FnMut(u8) -> bool,
-    {
-        let position = self.iter().position(|d| predicate(*d)).unwrap();
-        let value = self[position];
-        self.remove(position);
-        value
+// Which segment each wire is connected to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wiring {
+    pub segments: Vec<usize>,
+}
+
+impl Wiring {
+    // Turn a pattern of lit wires into the segments that they light up
+    pub fn segments_for(&self, pattern: u32) -> u32 {
+        (0..self.segments.len())
+            .filter(|wire| pattern & (1 << wire) != 0)
+            .fold(0, |segments, wire| segments | (1 << self.segments[wire]))
+    }
+
+    pub fn glyph_for(&self, font: &Font, pattern: u32) -> Option<char> {
+        font.glyph_for(self.segments_for(pattern))
     }
 }
 
-pub struct Note {
-    digits: Vec<u8>,
-    output: Vec<u8>,
+// Searches for every wiring that turns the patterns into glyphs. Each wire only keeps the segments that are
+// possible for it given the sizes of the patterns it's in and out of, the most constrained wires are tried
+// first, and a partial wiring is abandoned as soon as any pattern can no longer become a glyph of its size.
+struct WiringSolver<'a> {
+    font: &'a Font,
+    patterns: &'a [u32],
+    candidates: Vec<u32>,
+    // Wires in the order they get assigned
+    order: Vec<usize>,
+    segments: Vec<usize>,
+    solutions: Vec<Wiring>,
 }
 
-#[aoc_generator(day8)]
-pub fn generator(input: &str) -> Vec<Note> {
-    input
-        .lines()
-        .map(|line| {
-            let make_wires = |s: &str| {
-                s.chars()
-                    .map(parse_wire)
-                    .flatten()
-                    .reduce(|mask, value| mask | value)
-                    .unwrap()
-            };
-
-            let (digits, output) = line.split(" | ").collect_tuple().unwrap();
-            Note {
-                digits: digits
-                    .split_whitespace()
-                    .map(&make_wires)
-                    .collect::<Vec<_>>(),
-                output: output
-                    .split_whitespace()
-                    .map(&make_wires)
-                    .collect::<Vec<_>>(),
+impl<'a> WiringSolver<'a> {
+    fn new(font: &'a Font, patterns: &'a [u32]) -> Self {
+        let all_segments = (1 << font.num_segments) - 1;
+        let mut candidates = vec![all_segments; font.num_segments];
+        for pattern in patterns {
+            let inside = font
+                .glyphs_with_size(pattern.count_ones())
+                .fold(0, |mask, glyph| mask | glyph);
+            let outside = font
+                .glyphs_with_size(pattern.count_ones())
+                .fold(0, |mask, glyph| mask | (!glyph & all_segments));
+            for (wire, candidates) in candidates.iter_mut().enumerate() {
+                *candidates &= if pattern & (1 << wire) != 0 {
+                    inside
+                } else {
+                    outside
+                };
+            }
+        }
+        let order = (0..font.num_segments)
+            .sorted_by_key(|wire| candidates[*wire].count_ones())
+            .collect();
+
+        Self {
+            font,
+            patterns,
+            candidates,
+            order,
+            segments: vec![0; font.num_segments],
+            solutions: Vec::new(),
+        }
+    }
+
+    // Whether every pattern could still be a glyph given the first `num_assigned` wires in the order
+    fn is_consistent(&self, num_assigned: usize) -> bool {
+        self.patterns.iter().all(|pattern| {
+            let (mut lit, mut unlit) = (0, 0);
+            for wire in &self.order[..num_assigned] {
+                if pattern & (1 << wire) != 0 {
+                    lit |= 1 << self.segments[*wire];
+                } else {
+                    unlit |= 1 << self.segments[*wire];
+                }
             }
+            self.font
+                .glyphs_with_size(pattern.count_ones())
+                .any(|glyph| glyph & lit == lit && glyph & unlit == 0)
         })
-        .collect()
+    }
+
+    // Stops early once there are two solutions, since that's already too many
+    fn search(&mut self, num_assigned: usize, used: u32) {
+        if self.solutions.len() > 1 {
+            return;
+        }
+        if num_assigned == self.order.len() {
+            self.solutions.push(Wiring {
+                segments: self.segments.clone(),
+            });
+            return;
+        }
+
+        let wire = self.order[num_assigned];
+        for segment in 0..self.font.num_segments {
+            let bit = 1 << segment;
+            if self.candidates[wire] & bit == 0 || used & bit != 0 {
+                continue;
+            }
+            self.segments[wire] = segment;
+            if self.is_consistent(num_assigned + 1) {
+                self.search(num_assigned + 1, used | bit);
+            }
+        }
+    }
+}
+
+pub struct Note {
+    patterns: Vec<u32>,
+    output: Vec<u32>,
+}
+
+impl Note {
+    fn parse(line: &str) -> Result<Self> {
+        let (patterns, output) = line
+            .split(" | ")
+            .collect_tuple()
+            .ok_or_else(|| anyhow!("Expected patterns and output separated by ' | ': {}", line))?;
+        let parse_patterns = |s: &str| {
+            s.split_whitespace()
+                .map(parse_pattern)
+                .collect::<Result<Vec<_>>>()
+        };
+        Ok(Self {
+            patterns: parse_patterns(patterns)?,
+            output: parse_patterns(output)?,
+        })
+    }
+
+    // The only wiring that makes every pattern and output value a glyph of the font
+    pub fn solve(&self, font: &Font) -> Result<Wiring> {
+        let all_patterns = self
+            .patterns
+            .iter()
+            .chain(&self.output)
+            .copied()
+            .unique()
+            .collect::<Vec<_>>();
+        if all_patterns
+            .iter()
+            .any(|pattern| pattern >> font.num_segments != 0)
+        {
+            bail!("Pattern uses more than {} wires", font.num_segments);
+        }
+
+        let mut solver = WiringSolver::new(font, &all_patterns);
+        solver.search(0, 0);
+        match &solver.solutions[..] {
+            [wiring] => Ok(wiring.clone()),
+            [] => bail!("No wiring turns every pattern into a glyph"),
+            _ => bail!("More than one wiring turns every pattern into a glyph"),
+        }
+    }
+
+    pub fn decode_output(&self, font: &Font) -> Result<String> {
+        let wiring = self.solve(font)?;
+        // The solver only accepts wirings that turn the output into glyphs
+        Ok(self
+            .output
+            .iter()
+            .map(|pattern| wiring.glyph_for(font, *pattern).unwrap())
+            .collect())
+    }
+}
+
+#[aoc_generator(day8)]
+pub fn generator(input: &str) -> Result<Vec<Note>> {
+    input.lines().map(Note::parse).collect()
 }
 
 #[aoc(day8, part1)]
 pub fn part1(notes: &[Note]) -> usize {
     notes
-        .into_iter()
+        .iter()
         .map(|note| {
             note.output
                 .iter()
-                .filter_map(|number| match number.count_ones() {
-                    2 | 3 | 4 | 7 => Some(()),
-                    _ => None,
-                })
+                .filter(|pattern| matches!(pattern.count_ones(), 2 | 3 | 4 | 7))
                 .count()
         })
         .sum()
 }
 
 #[aoc(day8, part2)]
-pub fn part2(notes: &[Note]) -> usize {
-    let sum_of_all_numbers = notes
-        .into_iter()
-        .map(|note| {
-            let mut digits = note.digits.clone();
-            let one = digits.find_and_remove_digit(|d| d.count_ones() == 2);
-            let seven = digits.find_and_remove_digit(|d| d.count_ones() == 3);
-            let four = digits.find_and_remove_digit(|d| d.count_ones() == 4);
-            let eight = digits.find_and_remove_digit(|d| d.count_ones() == 7);
+pub fn part2(notes: &[Note]) -> Result<usize> {
+    let font = Font::seven_segment();
+    notes
+        .iter()
+        .map(|note| Ok(note.decode_output(&font)?.parse::<usize>()?))
+        .sum()
+}
 
-            let top_right_and_bottom_right = one;
-            let top_wire = seven & !top_right_and_bottom_right;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            let bottom_left_and_bottom = !(four | top_wire) & 0b1111111;
+    const INPUT: &str =
+        "be cfbegad cbdgef fgaecd cgeb fdcge agebfd fecdb fabcd edb | fdgacbe cefdb cefbgd gcbe\n\
+         edbfga begcd cbg gc gcadebf fbgde acbgfd abcde gfcbed gfec | fcgedb cgb dgebacf gc\n\
+         fgaebd cg bdaec gdafb agbcfd gdcbef bgcad gfac gcb cdgabef | cg cg fdcagb cbg\n\
+         fbegcd cbd adcefb dageb afcb bc aefdc ecdab fgdeca fcdbega | efabcd cedba gadfec cb\n\
+         aecbfdg fbg gf bafeg dbefa fcge gcbea fcaegb dgceab fcbdga | gecf egdcabf bgf bfgea\n\
+         fgeab ca afcebg bdacfeg cfaedg gcfdb baec bfadeg bafgc acf | gebdcfa ecba ca fadegcb\n\
+         dbcfg fgd bdegcaf fgec aegbdf ecdfab fbedc dacgb gdcebf gf | cefg dcbef fcge gbcadfe\n\
+         bdfegc cbegaf gecbf dfcage bdacg ed bedf ced adcbefg gebcd | ed bcgafe cdgba cbgef\n\
+         egadfb cdbfeg cegd fecab cgb gbdefca cg fgcdab egfdb bfceg | gbdfcae bgc cg cgb\n\
+         gcafb gcf dcaebfg ecagb gf abcdeg gaef cafbge fdbac fegbdc | fgae cfgab fg bagce";
 
-            let nine = digits.find_and_remove_digit(|d| {
-                d.count_ones() == 6 && (d & bottom_left_and_bottom).count_ones() == 1
-            });
-            let bottom = nine & bottom_left_and_bottom;
-            let bottom_left = !bottom & bottom_left_and_bottom;
+    #[test]
+    fn test_part1() {
+        assert_eq!(part1(&generator(INPUT).unwrap()), 26);
+    }
 
-            let six = digits.find_and_remove_digit(|d| {
-                d.count_ones() == 6 && (d & top_right_and_bottom_right).count_ones() == 1
-            });
-            let bottom_right = six & top_right_and_bottom_right;
-            let top_right = !bottom_right & top_right_and_bottom_right;
+    #[test]
+    fn test_part2() {
+        assert_eq!(part2(&generator(INPUT).unwrap()).unwrap(), 61229);
+    }
 
-            let zero = digits.find_and_remove_digit(|d| d.count_ones() == 6);
+    #[test]
+    fn test_wiring() {
+        let note = Note::parse(
+            "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf",
+        )
+        .unwrap();
+        // The wiring from the puzzle's worked example: wire d goes to the top segment, wire a to the upper right,
+        // and so on
+        assert_eq!(
+            note.solve(&Font::seven_segment()).unwrap(),
+            Wiring {
+                segments: vec![1, 2, 3, 0, 5, 6, 4]
+            }
+        );
+        assert_eq!(note.decode_output(&Font::seven_segment()).unwrap(), "5353");
+    }
 
-            let two = digits
-                .find_and_remove_digit(|d| d.count_ones() == 5 && (d & bottom_left) == bottom_left);
-            let three = digits
-                .find_and_remove_digit(|d| d.count_ones() == 5 && (d & top_right) == top_right);
-            let five = digits.find_and_remove_digit(|d| d.count_ones() == 5);
+    #[test]
+    fn test_unsolvable_notes() {
+        // Only the easy digits, which can't tell apart wires that always appear together
+        let ambiguous = Note::parse("ab dab eafb acedgfb | ab dab").unwrap();
+        let error = ambiguous
+            .solve(&Font::seven_segment())
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("More than one wiring"), "{}", error);
 
-            let values = [zero, one, two, three, four, five, six, seven, eight, nine];
+        // Two patterns that would both have to be a 1
+        let unsatisfiable = Note::parse("ab cd | ab").unwrap();
+        let error = unsatisfiable
+            .solve(&Font::seven_segment())
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("No wiring"), "{}", error);
 
-            note.output
+        assert!(generator("ab cd ab").is_err());
+        assert!(generator("ab 12 | ab").is_err());
+
+        // Wires beyond the end of the display
+        let too_wide = Note::parse("abcdefgh | ab").unwrap();
+        assert!(too_wide.solve(&Font::seven_segment()).is_err());
+    }
+
+    // A note with every glyph of the font shown through a scrambled wiring, followed by the given output
+    fn scrambled_note(font: &Font, wiring: &Wiring, output: &str) -> Note {
+        let scramble = |segments: u32| {
+            (0..font.num_segments)
+                .filter(|wire| segments & (1 << wiring.segments[*wire]) != 0)
+                .fold(0, |pattern, wire| pattern | (1 << wire))
+        };
+        Note {
+            patterns: font
+                .glyphs
                 .iter()
-                .map(|number| values.iter().position(|d| d == number).unwrap())
-                .reduce(|number, digit| number * 10 + digit)
-                .unwrap()
-        })
-        .sum::<usize>();
+                .map(|(_, segments)| scramble(*segments))
+                .collect(),
+            output: output
+                .chars()
+                .map(|c| scramble(font.glyphs.iter().find(|(g, _)| *g == c).unwrap().1))
+                .collect(),
+        }
+    }
 
-    sum_of_all_numbers
+    // Shift every wire along by a few segments, wrapping around at the end
+    fn rotated_wiring(num_segments: usize, by: usize) -> Wiring {
+        Wiring {
+            segments: (0..num_segments)
+                .map(|wire| (wire * 5 + by) % num_segments)
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_fonts() {
+        let cases = [
+            (Font::seven_segment_hex(), "dEAd"),
+            (Font::seven_segment_hex(), "C0FFEE"),
+            (Font::fourteen_segment(), "HELLO"),
+            (Font::sixteen_segment(), "Run°"),
+        ];
+        for (font, output) in cases {
+            let wiring = rotated_wiring(font.num_segments, 3);
+            let note = scrambled_note(&font, &wiring, output);
+            assert_eq!(note.solve(&font).unwrap(), wiring);
+            assert_eq!(note.decode_output(&font).unwrap(), output);
+        }
+    }
+
+    #[test]
+    fn test_font_tables() {
+        for font in [
+            Font::seven_segment(),
+            Font::seven_segment_hex(),
+            Font::fourteen_segment(),
+            Font::sixteen_segment(),
+        ] {
+            // Every glyph is distinct and every segment is used
+            assert!(font
+                .glyphs
+                .iter()
+                .map(|(_, segments)| segments)
+                .all_unique());
+            let used = font
+                .glyphs
+                .iter()
+                .fold(0, |used, (_, segments)| used | segments);
+            assert_eq!(used, (1 << font.num_segments) - 1);
+        }
+    }
 }
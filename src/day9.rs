@@ -1,108 +1,387 @@
-use aoc_2021::{AocMap, Point};
+use std::collections::VecDeque;
+use std::fmt::Debug;
+
+use aoc_2021::{AocMap, Offset, Point};
 use aoc_runner_derive::{aoc, aoc_generator};
 
 use itertools::Itertools;
 
+// What separates basins from each other, and which cells count as next to each other
+#[derive(Debug, Clone, Copy)]
+pub struct BasinRules {
+    // Cells at least this high aren't part of any basin
+    pub wall_height: u8,
+    pub neighbors: &'static [Offset],
+}
+
+impl BasinRules {
+    pub const PUZZLE: Self = Self {
+        wall_height: 9,
+        neighbors: &AocMap::<u8>::PLUS_NEIGHBORS,
+    };
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Basin {
+    pub low_point: Point,
+    // In the order they were reached from the low point
+    pub cells: Vec<Point>,
+    // Top left and bottom right corners, inclusive
+    pub bounding_box: (Point, Point),
+}
+
+impl Basin {
+    pub fn size(&self) -> usize {
+        self.cells.len()
+    }
+}
+
+// The puzzle only needs the basin sizes, the labels are for looking at the basins
+#[derive(Debug, Clone)]
+pub struct BasinReport {
+    // In reading order of their low points
+    pub basins: Vec<Basin>,
+    // Index of the basin that each cell belongs to. A cell that several basins climb up to belongs to the
+    // first of them.
+    pub labels: AocMap<Option<usize>>,
+}
+
+impl BasinReport {
+    // Each basin as a letter, cycling through the alphabet, with `#` for walls and `.` for cells outside of
+    // any basin
+    pub fn render(&self, map: &AocMap<u8>, rules: &BasinRules) -> String {
+        self.labels
+            .values
+            .chunks(self.labels.size.x)
+            .zip(map.values.chunks(map.size.x))
+            .map(|(labels, heights)| {
+                labels
+                    .iter()
+                    .zip(heights)
+                    .map(|(label, height)| match label {
+                        Some(basin) => (b'a' + (basin % 26) as u8) as char,
+                        None if *height >= rules.wall_height => '#',
+                        None => '.',
+                    })
+                    .collect::<String>()
+                    + "\n"
+            })
+            .collect()
+    }
+}
+
+// Where water goes from a cell
+#[derive(Clone, Copy, PartialEq)]
+pub enum Flow {
+    Wall,
+    // Water stays here
+    Sink,
+    // Water moves to the neighbor at this offset
+    Toward(Offset),
+}
+
+impl Debug for Flow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Wall => write!(f, "#"),
+            Self::Sink => write!(f, "o"),
+            Self::Toward(offset) => match (offset.x.signum(), offset.y.signum()) {
+                (0, -1) => write!(f, "↑"),
+                (-1, 0) => write!(f, "←"),
+                (1, 0) => write!(f, "→"),
+                (0, 1) => write!(f, "↓"),
+                (-1, -1) => write!(f, "↖"),
+                (1, -1) => write!(f, "↗"),
+                (-1, 1) => write!(f, "↙"),
+                (1, 1) => write!(f, "↘"),
+                _ => write!(f, "?"),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Watershed {
+    // Only for looking at, the catchments already say where everything drains to
+    pub flow: AocMap<Flow>,
+    // Cells where water stops, in reading order. A flat region with no way down only has one sink, its first
+    // cell in reading order.
+    pub sinks: Vec<Point>,
+    // Index into `sinks` of where the water from each cell ends up
+    pub catchments: AocMap<Option<usize>>,
+}
+
+impl Watershed {
+    // Number of cells draining into each sink
+    pub fn catchment_sizes(&self) -> Vec<usize> {
+        let mut sizes = vec![0; self.sinks.len()];
+        for sink in self.catchments.values.iter().flatten() {
+            sizes[*sink] += 1;
+        }
+        sizes
+    }
+}
+
 trait HeightMap {
-    fn get_num_neighbors(&self, p: Point) -> usize;
-    fn get_higher_neighbors(&self, p: Point) -> Vec<Point>;
+    fn get_higher_neighbors(&self, p: Point, neighbors: &[Offset]) -> Vec<Point>;
+    fn is_low_point(&self, p: Point, neighbors: &[Offset]) -> bool;
+    fn low_points(&self, neighbors: &[Offset]) -> Vec<Point>;
+    fn basin(&self, low_point: Point, rules: &BasinRules) -> Basin;
+    fn basins(&self, rules: &BasinRules) -> BasinReport;
+    fn flow_directions(&self, rules: &BasinRules) -> AocMap<Flow>;
+    fn watershed(&self, rules: &BasinRules) -> Watershed;
 }
 
 impl HeightMap for AocMap<u8> {
-    fn get_num_neighbors(&self, p: Point) -> usize {
-        let x_border = p.x == 0 || p.x == self.size.x - 1;
-        let y_border = p.y == 0 || p.y == self.size.y - 1;
-        Self::PLUS_NEIGHBORS.len() - x_border as usize - y_border as usize
+    fn get_higher_neighbors(&self, p: Point, neighbors: &[Offset]) -> Vec<Point> {
+        let point_height = self.get(p);
+        neighbors
+            .iter()
+            .filter_map(|neighbor_offset| self.get_relative(p, *neighbor_offset))
+            .filter(|neighbor| self.get(*neighbor) > point_height)
+            .collect()
     }
 
-    fn get_higher_neighbors(&self, p: Point) -> Vec<Point> {
+    // Lower than every neighbor that's on the map
+    fn is_low_point(&self, p: Point, neighbors: &[Offset]) -> bool {
         let point_height = self.get(p);
-        Self::PLUS_NEIGHBORS
+        neighbors
+            .iter()
+            .filter_map(|neighbor_offset| self.get_relative(p, *neighbor_offset))
+            .all(|neighbor| self.get(neighbor) > point_height)
+    }
+
+    fn low_points(&self, neighbors: &[Offset]) -> Vec<Point> {
+        self.coordinates()
+            .filter(|p| self.is_low_point(*p, neighbors))
+            .collect()
+    }
+
+    // Every cell that can be reached from the low point by only ever climbing, without going over a wall
+    fn basin(&self, low_point: Point, rules: &BasinRules) -> Basin {
+        let mut cells = vec![low_point];
+        let mut in_basin = AocMap {
+            size: self.size,
+            values: vec![false; self.values.len()],
+        };
+        in_basin.set(low_point, true);
+        let mut to_visit = VecDeque::from([low_point]);
+        while let Some(p) = to_visit.pop_front() {
+            for neighbor in self.get_higher_neighbors(p, rules.neighbors) {
+                if in_basin.get(neighbor) || self.get(neighbor) >= rules.wall_height {
+                    continue;
+                }
+                in_basin.set(neighbor, true);
+                cells.push(neighbor);
+                to_visit.push_back(neighbor);
+            }
+        }
+
+        let (min_x, max_x) = cells.iter().map(|p| p.x).minmax().into_option().unwrap();
+        let (min_y, max_y) = cells.iter().map(|p| p.y).minmax().into_option().unwrap();
+        Basin {
+            low_point,
+            cells,
+            bounding_box: (Point::new(min_x, min_y), Point::new(max_x, max_y)),
+        }
+    }
+
+    fn basins(&self, rules: &BasinRules) -> BasinReport {
+        let basins = self
+            .low_points(rules.neighbors)
             .into_iter()
-            .filter_map(|neighbor_offset| {
-                if let Some(neighbor) = self.get_relative(p, neighbor_offset) {
-                    let height = self.get(neighbor);
-                    if height > point_height {
-                        Some(neighbor)
-                    } else {
-                        None
+            .filter(|low_point| self.get(*low_point) < rules.wall_height)
+            .map(|low_point| self.basin(low_point, rules))
+            .collect::<Vec<_>>();
+
+        let mut labels = AocMap {
+            size: self.size,
+            values: vec![None; self.values.len()],
+        };
+        for (index, basin) in basins.iter().enumerate() {
+            for cell in &basin.cells {
+                if labels.get(*cell).is_none() {
+                    labels.set(*cell, Some(index));
+                }
+            }
+        }
+
+        BasinReport { basins, labels }
+    }
+
+    // Water flows down the steepest slope, where a diagonal step is √2 times as long as a straight one,
+    // taking the first of the neighbors in order on ties. Cells on a flat region without a lower neighbor
+    // flow toward the nearest cell of the region that does have one, or if there's none, toward the
+    // region's first cell in reading order, which becomes its sink.
+    fn flow_directions(&self, rules: &BasinRules) -> AocMap<Flow> {
+        let is_wall = |p: Point| self.get(p) >= rules.wall_height;
+        let neighbors = |p: Point| {
+            rules
+                .neighbors
+                .iter()
+                .filter_map(move |offset| Some((*offset, self.get_relative(p, *offset)?)))
+        };
+
+        let mut flow = AocMap {
+            size: self.size,
+            values: vec![Flow::Wall; self.values.len()],
+        };
+        // Cells that still need a direction
+        let mut undecided = AocMap {
+            size: self.size,
+            values: vec![false; self.values.len()],
+        };
+        for p in self.coordinates().filter(|p| !is_wall(*p)) {
+            let slope = |(offset, neighbor): &(Offset, Point)| {
+                let drop = self.get(p) as f64 - self.get(*neighbor) as f64;
+                drop / ((offset.x * offset.x + offset.y * offset.y) as f64).sqrt()
+            };
+            let steepest = neighbors(p)
+                .filter(|(_, neighbor)| self.get(*neighbor) < self.get(p))
+                .min_by(|a, b| slope(b).total_cmp(&slope(a)));
+            match steepest {
+                Some((offset, _)) => flow.set(p, Flow::Toward(offset)),
+                None => undecided.set(p, true),
+            }
+        }
+
+        // Which flat region each cell belongs to, shared by all of them so that a map full of tiny regions
+        // doesn't need a fresh grid for each one
+        let mut region_ids = AocMap {
+            size: self.size,
+            values: vec![None; self.values.len()],
+        };
+        let mut num_regions = 0;
+        for start in self.coordinates() {
+            if !undecided.get(start) {
+                continue;
+            }
+
+            // Everything at the same height connected to this cell, in the order it was found
+            let id = Some(num_regions);
+            num_regions += 1;
+            let height = self.get(start);
+            let mut region = vec![start];
+            region_ids.set(start, id);
+            let mut index = 0;
+            while let Some(p) = region.get(index).copied() {
+                for (_, neighbor) in neighbors(p) {
+                    if region_ids.get(neighbor) != id
+                        && !is_wall(neighbor)
+                        && self.get(neighbor) == height
+                    {
+                        region_ids.set(neighbor, id);
+                        region.push(neighbor);
                     }
-                } else {
-                    None
                 }
-            })
-            .collect()
+                index += 1;
+            }
+
+            // Spread out from the ways down, in reading order, with each cell pointing back to where it was
+            // reached from
+            let mut outlets = region
+                .iter()
+                .copied()
+                .filter(|p| !undecided.get(*p))
+                .sorted_by_key(|p| (p.y, p.x))
+                .collect::<VecDeque<_>>();
+            if outlets.is_empty() {
+                let sink = *region.iter().min_by_key(|p| (p.y, p.x)).unwrap();
+                flow.set(sink, Flow::Sink);
+                undecided.set(sink, false);
+                outlets.push_back(sink);
+            }
+            while let Some(p) = outlets.pop_front() {
+                for (offset, neighbor) in neighbors(p) {
+                    if region_ids.get(neighbor) == id && undecided.get(neighbor) {
+                        flow.set(neighbor, Flow::Toward(Offset::new(-offset.x, -offset.y)));
+                        undecided.set(neighbor, false);
+                        outlets.push_back(neighbor);
+                    }
+                }
+            }
+        }
+
+        flow
+    }
+
+    fn watershed(&self, rules: &BasinRules) -> Watershed {
+        let flow = self.flow_directions(rules);
+        let sinks = flow
+            .coordinates()
+            .filter(|p| flow.get(*p) == Flow::Sink)
+            .collect::<Vec<_>>();
+
+        let mut catchments = AocMap {
+            size: self.size,
+            values: vec![None; self.values.len()],
+        };
+        for (index, sink) in sinks.iter().enumerate() {
+            catchments.set(*sink, Some(index));
+        }
+        for start in self.coordinates() {
+            // Follow the water down until it reaches somewhere that's already known, then fill in the path
+            let mut path = Vec::new();
+            let mut p = start;
+            let catchment = loop {
+                if let Some(catchment) = catchments.get(p) {
+                    break Some(catchment);
+                }
+                match flow.get(p) {
+                    Flow::Toward(offset) => {
+                        path.push(p);
+                        p = self.get_relative(p, offset).unwrap();
+                    }
+                    Flow::Wall | Flow::Sink => break None,
+                }
+            };
+            for p in path {
+                catchments.set(p, catchment);
+            }
+        }
+
+        Watershed {
+            flow,
+            sinks,
+            catchments,
+        }
     }
 }
 
 #[aoc_generator(day9)]
 pub fn generator(input: &str) -> AocMap<u8> {
-    AocMap::<u8>::from_render(input, |c| match c {
-        c if c >= '0' && c <= '9' => Some(c as u8 - '0' as u8),
-        _ => None,
-    })
+    AocMap::<u8>::from_render(input, |c| c.to_digit(10).map(|height| height as u8))
 }
 
 #[aoc(day9, part1)]
 pub fn part1(map: &AocMap<u8>) -> usize {
-    map.coordinates()
-        .map(|p| {
-            let point_height = map.get(p);
-            let lower_neighbors = AocMap::<u8>::PLUS_NEIGHBORS
-                .into_iter()
-                .any(|neighbor_offset| {
-                    if let Some(neighbor) = map.get_relative(p, neighbor_offset) {
-                        map.get(neighbor) <= point_height
-                    } else {
-                        false
-                    }
-                });
-            if lower_neighbors {
-                0
-            } else {
-                1 + point_height as usize
-            }
-        })
+    map.low_points(&AocMap::<u8>::PLUS_NEIGHBORS)
+        .into_iter()
+        .map(|p| 1 + map.get(p) as usize)
         .sum::<usize>()
 }
 
 #[aoc(day9, part2)]
 pub fn part2(map: &AocMap<u8>) -> usize {
-    let basins = map
-        .coordinates()
-        .map(|p| {
-            let mut higher_neighbors = map.get_higher_neighbors(p);
-            if higher_neighbors.len() != map.get_num_neighbors(p) {
-                0
-            } else {
-                let mut basin = vec![p];
-                while let Some(neighbor) = higher_neighbors.pop() {
-                    if basin.contains(&neighbor) {
-                        continue;
-                    }
-
-                    if map.get(neighbor) == 9 {
-                        continue;
-                    }
-
-                    basin.push(neighbor);
-                    let mut neighbors_higher_neighbors = map.get_higher_neighbors(neighbor);
-                    higher_neighbors.append(&mut neighbors_higher_neighbors);
-                }
-
-                basin.len()
-            }
-        })
+    map.basins(&BasinRules::PUZZLE)
+        .basins
+        .iter()
+        .map(Basin::size)
         .sorted_unstable()
-        .rev();
-    let basins = basins.collect_vec();
-    let basins = basins.into_iter();
-
-    let top_three_basins = basins.take(3);
+        .rev()
+        .take(3)
+        .product()
+}
 
-    top_three_basins
-        .reduce(|product, elem| product * elem)
-        .unwrap_or(0)
+#[aoc(day9, part2, Watershed)]
+pub fn part2_watershed(map: &AocMap<u8>) -> usize {
+    map.watershed(&BasinRules::PUZZLE)
+        .catchment_sizes()
+        .into_iter()
+        .sorted_unstable()
+        .rev()
+        .take(3)
+        .product()
 }
 
 #[cfg(test)]
@@ -124,4 +403,159 @@ mod tests {
     fn test_part2() {
         assert_eq!(part2(&generator(INPUT)), 1134);
     }
+
+    #[test]
+    fn test_basin_report() {
+        let map = generator(INPUT);
+        let report = map.basins(&BasinRules::PUZZLE);
+        assert_eq!(
+            report
+                .basins
+                .iter()
+                .map(|basin| (basin.low_point, basin.size(), basin.bounding_box))
+                .collect_vec(),
+            vec![
+                (Point::new(1, 0), 3, (Point::new(0, 0), Point::new(1, 1))),
+                (Point::new(9, 0), 9, (Point::new(5, 0), Point::new(9, 2))),
+                (Point::new(2, 2), 14, (Point::new(0, 1), Point::new(5, 4))),
+                (Point::new(6, 4), 9, (Point::new(5, 2), Point::new(9, 4))),
+            ]
+        );
+        assert_eq!(
+            report.render(&map, &BasinRules::PUZZLE),
+            "aa###bbbbb\n\
+             a#ccc#b#bb\n\
+             #ccccc#d#b\n\
+             ccccc#ddd#\n\
+             #c###ddddd\n"
+        );
+    }
+
+    #[test]
+    fn test_basin_rules() {
+        let map = generator(INPUT);
+        // Lower walls cut off the parts of basins that are at least that high
+        let low_walls = BasinRules {
+            wall_height: 7,
+            ..BasinRules::PUZZLE
+        };
+        let sizes = |rules: &BasinRules| {
+            map.basins(rules)
+                .basins
+                .iter()
+                .map(Basin::size)
+                .collect_vec()
+        };
+        assert_eq!(sizes(&low_walls), vec![3, 9, 3, 4]);
+
+        // With diagonals the basins can climb through the gaps between walls, so they overlap, and the shared
+        // cells are labelled with the first basin that reaches them
+        let diagonal = BasinRules {
+            neighbors: &AocMap::<u8>::ALL_NEIGHBORS,
+            ..BasinRules::PUZZLE
+        };
+        assert_eq!(sizes(&diagonal), vec![5, 13, 14, 11]);
+        let report = map.basins(&diagonal);
+        let num_labelled = report.labels.values.iter().flatten().count();
+        let num_below_walls = map.values.iter().filter(|height| **height < 9).count();
+        assert_eq!(num_labelled, num_below_walls);
+        assert_eq!(report.labels.get(Point::new(2, 0)), None);
+        assert_eq!(report.labels.get(Point::new(0, 1)), Some(0));
+    }
+
+    #[test]
+    fn test_watershed() {
+        let map = generator(INPUT);
+        let watershed = map.watershed(&BasinRules::PUZZLE);
+        assert_eq!(
+            watershed.sinks,
+            map.low_points(&AocMap::<u8>::PLUS_NEIGHBORS)
+        );
+        assert_eq!(watershed.catchment_sizes(), vec![3, 9, 14, 9]);
+        assert_eq!(part2_watershed(&map), 1134);
+        assert_eq!(
+            format!("{:?}", watershed.flow),
+            "→o###→→→→o\n\
+             ↑#↓↓←#↑#↑↑\n\
+             #→o←←←#↓#↑\n\
+             →→↑↑↑#↓←←#\n\
+             #↑###→o←←←\n"
+        );
+    }
+
+    #[test]
+    fn test_plateaus() {
+        // Climbing from the low point never reaches the rest of the 5s, but water on them still drains there
+        let map = generator(
+            "99999\n\
+             95559\n\
+             95519\n\
+             99999",
+        );
+        let watershed = map.watershed(&BasinRules::PUZZLE);
+        assert_eq!(
+            format!("{:?}", watershed.flow),
+            "#####\n\
+             #→→↓#\n\
+             #→→o#\n\
+             #####\n"
+        );
+        assert_eq!(watershed.catchment_sizes(), vec![6]);
+        assert_eq!(
+            map.basins(&BasinRules::PUZZLE)
+                .basins
+                .iter()
+                .map(Basin::size)
+                .collect_vec(),
+            vec![3]
+        );
+
+        // A flat region with no way out drains into its first cell
+        let map = generator(
+            "9999\n\
+             9229\n\
+             9229\n\
+             9999",
+        );
+        let watershed = map.watershed(&BasinRules::PUZZLE);
+        assert_eq!(watershed.sinks, vec![Point::new(1, 1)]);
+        assert_eq!(
+            format!("{:?}", watershed.flow),
+            "####\n\
+             #o←#\n\
+             #↑↑#\n\
+             ####\n"
+        );
+        assert_eq!(watershed.catchment_sizes(), vec![4]);
+    }
+
+    #[test]
+    fn test_diagonal_flow() {
+        let map = generator(
+            "321\n\
+             432\n\
+             543",
+        );
+        let rules = BasinRules {
+            neighbors: &AocMap::<u8>::ALL_NEIGHBORS,
+            ..BasinRules::PUZZLE
+        };
+        assert_eq!(
+            format!("{:?}", map.flow_directions(&rules)),
+            "→→o\n\
+             ↗↗↑\n\
+             ↗↗↑\n"
+        );
+
+        // Dropping 4 over a diagonal step is less steep than dropping 3 over a straight one
+        let map = generator(
+            "888\n\
+             885\n\
+             884",
+        );
+        assert_eq!(
+            map.flow_directions(&rules).get(Point::new(1, 1)),
+            Flow::Toward(Offset::new(1, 0))
+        );
+    }
 }
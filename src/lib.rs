@@ -1,4 +1,5 @@
 use std::fmt::Debug;
+use std::ops::{Add, Sub};
 
 use itertools::iproduct;
 
@@ -108,6 +109,17 @@ where
         }
     }
 
+    // Same as get_relative, but treats the map as a torus where moving off one edge wraps around to the other
+    pub fn get_relative_wrapping(&self, point: Point, offset: Offset) -> Point {
+        let wrap = |base: usize, offset: isize, size: usize| {
+            (base as isize + offset).rem_euclid(size as isize) as usize
+        };
+        Point::new(
+            wrap(point.x, offset.x, self.size.x),
+            wrap(point.y, offset.y, self.size.y),
+        )
+    }
+
     pub fn coordinates(&self) -> impl Iterator<Item = Point> {
         iproduct!(0..self.size.y, 0..self.size.x).map(|(y, x)| Point::new(x, y))
     }
@@ -138,3 +150,36 @@ where
         }
     }
 }
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point3 {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl Point3 {
+    pub const fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+
+    pub fn manhattan_distance(&self, other: &Self) -> i32 {
+        (self.x - other.x).abs() + (self.y - other.y).abs() + (self.z - other.z).abs()
+    }
+}
+
+impl Add for Point3 {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+    }
+}
+
+impl Sub for Point3 {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
+    }
+}
@@ -4,13 +4,30 @@ mod day1;
 mod day10;
 mod day11;
 mod day13;
+mod day19;
 mod day2;
+mod day20;
+mod day21;
+mod day22;
+mod day23;
+mod day24;
+mod day25;
 mod day3;
+
+// Besides solving the puzzle, these days have APIs for exploring it further that only their tests use
+#[allow(dead_code)]
+mod day18;
+#[allow(dead_code)]
 mod day4;
+#[allow(dead_code)]
 mod day5;
+#[allow(dead_code)]
 mod day6;
+#[allow(dead_code)]
 mod day7;
+#[allow(dead_code)]
 mod day8;
+#[allow(dead_code)]
 mod day9;
 
 aoc_main! { year = 2021 }